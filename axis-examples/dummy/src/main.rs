@@ -2,7 +2,7 @@ use axis::{
     Matrix,
     domain::random_provider,
     math::{Activation, Loss, Optimizer},
-    mlp::{Dense, MultiLayerPerceptron},
+    mlp::{Control, Dense, EpochLog, MultiLayerPerceptron, Trainer},
 };
 
 fn main() {
@@ -25,17 +25,24 @@ fn main() {
         .collect::<Vec<Matrix<f32>>>();
 
     let mut mlp = MultiLayerPerceptron::new()
-        .layer(Dense::new((2, 16), Activation::Sigmoid))
-        .layer(Dense::new((16, 16), Activation::ReLU))
-        .layer(Dense::new((16, 1), Activation::Sigmoid));
+        .layer(Dense::new((2, 16)).activation(Activation::Sigmoid))
+        .layer(Dense::new((16, 16)).activation(Activation::ReLU))
+        .layer(Dense::new((16, 1)).activation(Activation::Sigmoid));
 
     let loss = Loss::MSE;
-    let optimizer = Optimizer::SGD(0.03);
+    let optimizer = Optimizer::adam(0.01);
 
     let start_time = std::time::Instant::now();
-    for _ in 0..500 {
-        mlp.fit(&features, &targets, &optimizer, &loss);
-    }
+    let history = Trainer::new(optimizer, loss)
+        .epochs(500)
+        .callback(|_: &MultiLayerPerceptron, log: &EpochLog| {
+            if log.epoch.is_multiple_of(100) {
+                println!("Epoch {}: loss {}", log.epoch, log.loss);
+            }
+            Control::Continue
+        })
+        .fit(&mut mlp, &features, &targets);
+    println!("Final loss: {:?}", history.last_loss());
     let elapsed_time = start_time.elapsed();
     println!("Time taken: {:?}", elapsed_time);
 
@@ -44,7 +51,7 @@ fn main() {
 
         println!("Output: {:?}", prediction);
         println!("Expected: {:?}", output);
-        println!("Loss: {:?}", loss.apply(output, &prediction));
+        println!("Loss: {:?}", loss.value(output, &prediction));
         println!("----------------------------------");
         println!();
     }
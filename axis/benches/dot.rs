@@ -0,0 +1,49 @@
+use axis::Matrix;
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use std::{
+    hint::black_box,
+    ops::{Add, Mul},
+    time::Duration,
+};
+
+/// The triple loop `Matrix::dot` used before the blocked kernel, kept as the baseline.
+fn naive_dot<T>(a: &Matrix<T>, b: &Matrix<T>) -> Matrix<T>
+where
+    T: Default + Clone + Add<Output = T> + Mul<Output = T>,
+{
+    let mut result = Matrix::new(a.rows(), b.cols());
+    for i in 0..a.rows() {
+        for j in 0..b.cols() {
+            let mut sum = T::default();
+            for k in 0..a.cols() {
+                sum = sum + a[(i, k)].clone() * b[(k, j)].clone();
+            }
+
+            result[(i, j)] = sum;
+        }
+    }
+
+    result
+}
+
+fn dot(c: &mut Criterion) {
+    let mut group = c.benchmark_group("dot");
+    group.sample_size(10).warm_up_time(Duration::from_secs(1));
+
+    for size in [64, 256, 1024] {
+        let a = Matrix::random((size, size), -1.0..1.0);
+        let b = Matrix::random((size, size), -1.0..1.0);
+
+        group.bench_with_input(BenchmarkId::new("naive", size), &size, |bench, _| {
+            bench.iter(|| naive_dot(black_box(&a), black_box(&b)))
+        });
+        group.bench_with_input(BenchmarkId::new("blocked", size), &size, |bench, _| {
+            bench.iter(|| black_box(&a).dot(black_box(&b)))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, dot);
+criterion_main!(benches);
@@ -1,17 +1,19 @@
 use super::FrameIterator;
 use crate::core::series::Series;
 use std::{
+    borrow::Cow,
     collections::BTreeMap,
     ops::{Index, IndexMut},
 };
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct DataFrame {
-    series: BTreeMap<&'static str, Series>,
-    column_order: Vec<&'static str>,
+    series: BTreeMap<Cow<'static, str>, Series>,
+    column_order: Vec<Cow<'static, str>>,
 }
 
 impl DataFrame {
+    #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
         DataFrame {
             series: BTreeMap::new(),
@@ -21,15 +23,16 @@ impl DataFrame {
 
     pub fn push<T: Into<Series>>(&mut self, series: T) {
         let new_series = series.into();
-        self.column_order.push(new_series.name);
-        self.series.insert(new_series.name, new_series);
+        self.column_order.push(new_series.name.clone());
+        self.series.insert(new_series.name.clone(), new_series);
     }
 
-    pub fn set<T: Into<Series>>(&mut self, name: &'static str, series: T) {
-        let new_series = series.into().rename(name);
+    pub fn set<T: Into<Series>>(&mut self, name: impl Into<Cow<'static, str>>, series: T) {
+        let name = name.into();
+        let new_series = series.into().rename(name.clone());
 
-        if !self.series.contains_key(name) {
-            self.column_order.push(name);
+        if !self.series.contains_key(&name) {
+            self.column_order.push(name.clone());
         }
 
         self.series.insert(name, new_series);
@@ -48,20 +51,46 @@ impl DataFrame {
     }
 }
 
-impl Index<&'static str> for DataFrame {
+impl Index<&str> for DataFrame {
     type Output = Series;
 
-    fn index(&self, name: &'static str) -> &Self::Output {
+    fn index(&self, name: &str) -> &Self::Output {
         self.series.get(name).expect("Column not found")
     }
 }
 
-impl IndexMut<&'static str> for DataFrame {
-    fn index_mut(&mut self, name: &'static str) -> &mut Self::Output {
+impl IndexMut<&str> for DataFrame {
+    fn index_mut(&mut self, name: &str) -> &mut Self::Output {
         self.series.get_mut(name).expect("Column not found")
     }
 }
 
+/// A frame is serialized as its list of columns, in column order.
+#[cfg(feature = "serde")]
+impl serde::Serialize for DataFrame {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for DataFrame {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let mut frame = DataFrame::new();
+        for series in Vec::<Series>::deserialize(deserializer)? {
+            frame.push(series);
+        }
+
+        Ok(frame)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -96,4 +125,24 @@ mod tests {
         frame["column1"].push(42);
         assert_eq!(frame["column1"].len(), 1);
     }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_serde() {
+        let mut frame = DataFrame::new();
+        frame.set("b", vec![1, 2, 3]);
+        frame.set("a", vec!["x", "y", "z"]);
+
+        let json = serde_json::to_string(&frame).unwrap();
+        let restored = serde_json::from_str::<DataFrame>(&json).unwrap();
+
+        assert_eq!(restored, frame);
+        assert_eq!(
+            restored
+                .iter()
+                .map(|series| series.name.as_ref())
+                .collect::<Vec<_>>(),
+            vec!["b", "a"]
+        );
+    }
 }
@@ -1,15 +1,18 @@
-use std::collections::BTreeMap;
+use std::{borrow::Cow, collections::BTreeMap};
 
 use super::Series;
 
 pub struct FrameIterator<'a> {
-    series: &'a BTreeMap<&'static str, Series>,
-    order: &'a Vec<&'static str>,
+    series: &'a BTreeMap<Cow<'static, str>, Series>,
+    order: &'a Vec<Cow<'static, str>>,
     index: usize,
 }
 
 impl<'a> FrameIterator<'a> {
-    pub fn new(series: &'a BTreeMap<&'static str, Series>, order: &'a Vec<&'static str>) -> Self {
+    pub fn new(
+        series: &'a BTreeMap<Cow<'static, str>, Series>,
+        order: &'a Vec<Cow<'static, str>>,
+    ) -> Self {
         FrameIterator {
             series,
             order,
@@ -23,7 +26,7 @@ impl<'a> Iterator for FrameIterator<'a> {
 
     fn next(&mut self) -> Option<Self::Item> {
         if self.index < self.order.len() {
-            let name = self.order[self.index];
+            let name = &self.order[self.index];
             self.index += 1;
             Some(&self.series[name])
         } else {
@@ -1,4 +1,5 @@
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DataType {
     None,
     I8,
@@ -18,6 +19,7 @@ pub enum DataType {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Scaler {
     Empty,
     I8(i8),
@@ -37,6 +39,7 @@ pub enum Scaler {
 }
 
 impl Scaler {
+    #[allow(clippy::match_like_matches_macro)]
     pub fn is_empty(&self) -> bool {
         match self {
             Scaler::Empty => true,
@@ -44,6 +47,7 @@ impl Scaler {
         }
     }
 
+    #[allow(clippy::match_like_matches_macro)]
     pub fn is_numeric(&self) -> bool {
         match self {
             Scaler::I8(_)
@@ -177,6 +181,7 @@ mod test {
     use super::*;
 
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn test_is_empty() {
         let value = Scaler::Empty;
         assert_eq!(value.is_empty(), true);
@@ -1,17 +1,21 @@
 use super::{DataType, Scaler};
-use std::ops::{Index, IndexMut};
+use std::{
+    borrow::Cow,
+    ops::{Index, IndexMut},
+};
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Series {
-    pub name: &'static str,
+    pub name: Cow<'static, str>,
     pub data_type: DataType,
     pub values: Vec<Scaler>,
 }
 
 impl Series {
-    pub fn new(name: &'static str) -> Self {
+    pub fn new(name: impl Into<Cow<'static, str>>) -> Self {
         Series {
-            name,
+            name: name.into(),
             data_type: DataType::None,
             values: Vec::new(),
         }
@@ -34,8 +38,8 @@ impl Series {
         }
     }
 
-    pub fn rename(mut self, name: &'static str) -> Self {
-        self.name = name;
+    pub fn rename(mut self, name: impl Into<Cow<'static, str>>) -> Self {
+        self.name = name.into();
         self
     }
 
@@ -83,6 +87,41 @@ where
     }
 }
 
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Series {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::Error;
+
+        #[derive(serde::Deserialize)]
+        struct RawSeries {
+            name: String,
+            data_type: DataType,
+            values: Vec<Scaler>,
+        }
+
+        let raw = RawSeries::deserialize(deserializer)?;
+        if let Some(value) = raw
+            .values
+            .iter()
+            .find(|value| value.is_empty() || value.data_type() != raw.data_type)
+        {
+            return Err(D::Error::custom(format!(
+                "Invalid value ({:?}) in column of type ({:?})",
+                value, raw.data_type
+            )));
+        }
+
+        Ok(Series {
+            name: Cow::Owned(raw.name),
+            data_type: raw.data_type,
+            values: raw.values,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -110,6 +149,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn test_is_empty() {
         let column = Series::new("empty");
         assert_eq!(column.is_empty(), true);
@@ -120,4 +160,18 @@ mod tests {
         let column: Series = vec![1, 2, 3].into();
         assert_eq!(column.len(), 3);
     }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_serde() {
+        let column = Series::from(vec![1, 2, 3]).rename("numbers");
+        let json = serde_json::to_string(&column).unwrap();
+
+        let restored = serde_json::from_str::<Series>(&json).unwrap();
+        assert_eq!(restored, column);
+        assert!(matches!(restored.name, Cow::Owned(_)));
+
+        let mixed = r#"{"name":"bad","data_type":"I32","values":[{"I32":1},{"Bool":true}]}"#;
+        assert!(serde_json::from_str::<Series>(mixed).is_err());
+    }
 }
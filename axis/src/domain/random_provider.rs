@@ -50,6 +50,15 @@ pub fn scoped_seed<R>(seed: u64, f: impl FnOnce() -> R) -> R {
     })
 }
 
+/// Runs the closure `f` and then restores the thread-local random number generator to its
+/// state before the call, so whatever `f` draws does not shift the sequence seen afterwards.
+pub fn preserve_state<R>(f: impl FnOnce() -> R) -> R {
+    let original = TLS_RNG.with(|cell| cell.borrow().clone());
+    let result = f();
+    TLS_RNG.with(|cell| *cell.borrow_mut() = original);
+    result
+}
+
 ///
 /// For floating point types, the number will be in the range [0, 1).
 /// For integer types, the number will be in the range [0, MAX).
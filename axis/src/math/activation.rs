@@ -1,3 +1,6 @@
+use super::Matrix;
+use std::{fmt, str::FromStr};
+
 const MAX: f32 = 1e10;
 const MIN: f32 = -1e10;
 
@@ -9,34 +12,79 @@ fn clamp(x: f32) -> f32 {
     x.clamp(MIN, MAX)
 }
 
+const SELU_ALPHA: f32 = 1.673_263_2;
+const SELU_SCALE: f32 = 1.050_701;
+const GELU_COEFFICIENT: f32 = 0.044_715;
+
+fn sigmoid(x: f32) -> f32 {
+    1.0 / (1.0 + (-x).exp())
+}
+
+fn softplus(x: f32) -> f32 {
+    x.max(0.0) + (-x.abs()).exp().ln_1p()
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Activation {
     Sigmoid,
     ReLU,
-    LeakyReLU,
+    /// ReLU with the given slope for negative inputs.
+    LeakyReLU(f32),
     Tanh,
     Softmax,
     Linear,
+    /// Gaussian error linear unit, using the tanh approximation.
+    GELU,
+    /// Sigmoid-weighted linear unit, also known as Swish.
+    SiLU,
+    /// Exponential linear unit with the given `alpha`.
+    ELU(f32),
+    SELU,
+    Softplus,
+    Mish,
+    HardSigmoid,
 }
 
 impl Activation {
+    /// Applies the activation to a single value. `Softmax` is defined over a whole row, so
+    /// here it treats `x` as a row of one value and gives 1; [`Activation::forward`]
+    /// normalizes whole rows.
     pub fn activate(&self, x: f32) -> f32 {
-        match self {
-            Activation::Sigmoid => clamp(1.0 / (1.0 + (-x).exp())),
+        match *self {
+            Activation::Sigmoid => clamp(sigmoid(x)),
             Activation::ReLU => clamp(x.max(0.0)),
-            Activation::LeakyReLU => clamp(x.max(0.01 * x)),
+            Activation::LeakyReLU(alpha) => clamp(if x > 0.0 { x } else { alpha * x }),
             Activation::Tanh => clamp(x.tanh()),
-            Activation::Softmax => {
-                let exp_x = x.exp();
-                clamp(exp_x / (1.0 + exp_x))
-            }
+            Activation::Softmax => 1.0,
             Activation::Linear => clamp(x),
+            Activation::GELU => {
+                let inner = std::f32::consts::FRAC_2_PI.sqrt() * (x + GELU_COEFFICIENT * x.powi(3));
+                clamp(0.5 * x * (1.0 + inner.tanh()))
+            }
+            Activation::SiLU => clamp(x * sigmoid(x)),
+            Activation::ELU(alpha) => clamp(if x > 0.0 { x } else { alpha * x.exp_m1() }),
+            Activation::SELU => clamp(if x > 0.0 {
+                SELU_SCALE * x
+            } else {
+                SELU_SCALE * SELU_ALPHA * x.exp_m1()
+            }),
+            Activation::Softplus => clamp(softplus(x)),
+            Activation::Mish => clamp(x * softplus(x).tanh()),
+            Activation::HardSigmoid => clamp((x / 6.0 + 0.5).clamp(0.0, 1.0)),
         }
     }
 
+    /// The derivative of the activation at the pre-activation input `x`, i.e. the same
+    /// value that was passed to [`Activation::activate`], not its output. For `Softmax` this
+    /// is the derivative of a row of one value, which is 0; [`Activation::backward`] applies
+    /// the Jacobian of whole rows.
     pub fn deactivate(&self, x: f32) -> f32 {
-        match self {
-            Activation::Sigmoid => clamp(x * (1.0 - x)),
+        match *self {
+            Activation::Sigmoid => {
+                let s = sigmoid(x);
+                clamp(s * (1.0 - s))
+            }
             Activation::ReLU => {
                 if x > 0.0 {
                     1.0
@@ -44,19 +92,307 @@ impl Activation {
                     0.0
                 }
             }
-            Activation::LeakyReLU => {
+            Activation::LeakyReLU(alpha) => {
+                if x > 0.0 {
+                    1.0
+                } else {
+                    alpha
+                }
+            }
+            Activation::Tanh => clamp(1.0 - x.tanh().powi(2)),
+            Activation::Softmax => 0.0,
+            Activation::Linear => 1.0,
+            Activation::GELU => {
+                let scale = std::f32::consts::FRAC_2_PI.sqrt();
+                let t = (scale * (x + GELU_COEFFICIENT * x.powi(3))).tanh();
+                let inner = scale * (1.0 + 3.0 * GELU_COEFFICIENT * x.powi(2));
+                clamp(0.5 * (1.0 + t) + 0.5 * x * (1.0 - t * t) * inner)
+            }
+            Activation::SiLU => {
+                let s = sigmoid(x);
+                clamp(s * (1.0 + x * (1.0 - s)))
+            }
+            Activation::ELU(alpha) => {
                 if x > 0.0 {
                     1.0
                 } else {
-                    0.01
+                    clamp(alpha * x.exp())
                 }
             }
-            Activation::Tanh => clamp(1.0 - x.powi(2)),
+            Activation::SELU => {
+                if x > 0.0 {
+                    SELU_SCALE
+                } else {
+                    clamp(SELU_SCALE * SELU_ALPHA * x.exp())
+                }
+            }
+            Activation::Softplus => clamp(sigmoid(x)),
+            Activation::Mish => {
+                let t = softplus(x).tanh();
+                clamp(t + x * (1.0 - t * t) * sigmoid(x))
+            }
+            Activation::HardSigmoid => {
+                if x > -3.0 && x < 3.0 {
+                    1.0 / 6.0
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+
+    /// Applies the activation to every row of `input`. Softmax is normalized per row,
+    /// every other activation is applied element-wise.
+    pub fn forward(&self, input: &Matrix<f32>) -> Matrix<f32> {
+        let mut output = input.clone();
+        match self {
             Activation::Softmax => {
-                let exp_x = x.exp();
-                clamp(exp_x * (1.0 - exp_x))
+                for row in output.as_mut().chunks_mut(input.cols().max(1)) {
+                    softmax(row);
+                }
+            }
+            _ => {
+                for value in output.iter_mut() {
+                    *value = self.activate(*value);
+                }
+            }
+        }
+
+        output
+    }
+
+    /// Maps the `error` with respect to the activated `output` back to the error with
+    /// respect to the pre-activation `input`. Softmax applies its full Jacobian per row
+    /// using the `output`; every other activation uses [`Activation::deactivate`] on the
+    /// `input`.
+    pub fn backward(
+        &self,
+        input: &Matrix<f32>,
+        output: &Matrix<f32>,
+        error: &Matrix<f32>,
+    ) -> Matrix<f32> {
+        if input.shape() != error.shape() || output.shape() != error.shape() {
+            panic!("Matrix dimensions do not match");
+        }
+
+        let mut delta = error.clone();
+        match self {
+            Activation::Softmax => {
+                let cols = output.cols().max(1);
+                let rows = output.as_ref().chunks(cols);
+                for (delta, output) in delta.as_mut().chunks_mut(cols).zip(rows) {
+                    let dot = delta
+                        .iter()
+                        .zip(output.iter())
+                        .map(|(d, s)| d * s)
+                        .sum::<f32>();
+
+                    for (d, s) in delta.iter_mut().zip(output.iter()) {
+                        *d = s * (*d - dot);
+                    }
+                }
+            }
+            _ => {
+                for (d, input) in delta.iter_mut().zip(input.iter()) {
+                    *d *= self.deactivate(*input);
+                }
+            }
+        }
+
+        delta
+    }
+}
+
+/// Formats the activation the same way it is written in Rust, e.g. `ReLU` or
+/// `LeakyReLU(0.01)`, so it can be parsed back with [`FromStr`].
+impl fmt::Display for Activation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl FromStr for Activation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (name, param) = match s.split_once('(') {
+            Some((name, rest)) => {
+                let param = rest
+                    .strip_suffix(')')
+                    .and_then(|param| param.trim().parse::<f32>().ok())
+                    .ok_or_else(|| format!("Invalid activation parameter: {s}"))?;
+                (name, Some(param))
+            }
+            None => (s, None),
+        };
+
+        match (name, param) {
+            ("Sigmoid", None) => Ok(Activation::Sigmoid),
+            ("ReLU", None) => Ok(Activation::ReLU),
+            ("LeakyReLU", Some(alpha)) => Ok(Activation::LeakyReLU(alpha)),
+            ("Tanh", None) => Ok(Activation::Tanh),
+            ("Softmax", None) => Ok(Activation::Softmax),
+            ("Linear", None) => Ok(Activation::Linear),
+            ("GELU", None) => Ok(Activation::GELU),
+            ("SiLU", None) => Ok(Activation::SiLU),
+            ("ELU", Some(alpha)) => Ok(Activation::ELU(alpha)),
+            ("SELU", None) => Ok(Activation::SELU),
+            ("Softplus", None) => Ok(Activation::Softplus),
+            ("Mish", None) => Ok(Activation::Mish),
+            ("HardSigmoid", None) => Ok(Activation::HardSigmoid),
+            _ => Err(format!("Unknown activation: {s}")),
+        }
+    }
+}
+
+fn softmax(row: &mut [f32]) {
+    let max = row.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+
+    let mut sum = 0.0;
+    for value in row.iter_mut() {
+        *value = (*value - max).exp();
+        sum += *value;
+    }
+
+    for value in row.iter_mut() {
+        *value /= sum;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_softmax_forward() {
+        let input = Matrix::from(vec![vec![1.0, 2.0, 3.0], vec![1000.0, 1001.0, 1002.0]]);
+        let output = Activation::Softmax.forward(&input);
+
+        let expected = [0.09003057, 0.24472847, 0.66524096];
+        for i in 0..2 {
+            let sum = (0..3).map(|j| output[(i, j)]).sum::<f32>();
+            assert!((sum - 1.0).abs() < 1e-6);
+
+            for j in 0..3 {
+                assert!((output[(i, j)] - expected[j]).abs() < 1e-6);
             }
-            Activation::Linear => 1.0,
         }
     }
+
+    #[test]
+    fn test_softmax_backward() {
+        let input = Matrix::from(vec![vec![0.5, -1.0, 2.0]]);
+        let error = Matrix::from(vec![vec![0.3, -0.7, 0.2]]);
+        let output = Activation::Softmax.forward(&input);
+        let delta = Activation::Softmax.backward(&input, &output, &error);
+
+        // Finite differences of sum(error * softmax(x)) with respect to each input.
+        let epsilon = 1e-3;
+        for j in 0..3 {
+            let mut plus = input.clone();
+            let mut minus = input.clone();
+            plus[(0, j)] += epsilon;
+            minus[(0, j)] -= epsilon;
+
+            let objective = |x: &Matrix<f32>| {
+                let y = Activation::Softmax.forward(x);
+                y.iter().zip(error.iter()).map(|(y, e)| y * e).sum::<f32>()
+            };
+
+            let numeric = (objective(&plus) - objective(&minus)) / (2.0 * epsilon);
+            assert!((numeric - delta[(0, j)]).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_element_wise_forward() {
+        let input = Matrix::from(vec![vec![-1.0, 2.0]]);
+        let output = Activation::ReLU.forward(&input);
+
+        assert_eq!(output, Matrix::from(vec![vec![0.0, 2.0]]));
+    }
+
+    fn derivative_check(activation: Activation, points: &[f32]) {
+        let epsilon = 1e-3;
+        for &x in points {
+            let numeric = (activation.activate(x + epsilon) - activation.activate(x - epsilon))
+                / (2.0 * epsilon);
+            let analytic = activation.deactivate(x);
+
+            assert!(
+                (numeric - analytic).abs() < 1e-2,
+                "{activation:?} at {x}: numeric {numeric}, analytic {analytic}"
+            );
+        }
+    }
+
+    const POINTS: [f32; 8] = [-4.0, -2.5, -1.0, -0.3, 0.2, 0.7, 1.5, 3.5];
+
+    #[test]
+    fn test_activation_derivatives() {
+        for activation in [
+            Activation::Sigmoid,
+            Activation::ReLU,
+            Activation::LeakyReLU(0.01),
+            Activation::LeakyReLU(0.2),
+            Activation::Tanh,
+            Activation::Linear,
+            Activation::GELU,
+            Activation::SiLU,
+            Activation::ELU(1.0),
+            Activation::ELU(0.5),
+            Activation::SELU,
+            Activation::Softplus,
+            Activation::Mish,
+            Activation::HardSigmoid,
+        ] {
+            derivative_check(activation, &POINTS);
+        }
+    }
+
+    #[test]
+    fn test_activation_from_str() {
+        for activation in [
+            Activation::Sigmoid,
+            Activation::LeakyReLU(0.01),
+            Activation::Softmax,
+            Activation::ELU(1.5),
+            Activation::HardSigmoid,
+        ] {
+            assert_eq!(activation.to_string().parse::<Activation>(), Ok(activation));
+        }
+
+        assert!("Swish".parse::<Activation>().is_err());
+        assert!("LeakyReLU".parse::<Activation>().is_err());
+        assert!("ReLU(0.1)".parse::<Activation>().is_err());
+    }
+
+    #[test]
+    fn test_softmax_single_value() {
+        for x in POINTS {
+            let row = Matrix::from(vec![vec![x]]);
+            let output = Activation::Softmax.forward(&row);
+            let delta = Activation::Softmax.backward(&row, &output, &Matrix::from(vec![vec![1.0]]));
+
+            assert_eq!(Activation::Softmax.activate(x), output[(0, 0)]);
+            assert_eq!(Activation::Softmax.deactivate(x), delta[(0, 0)]);
+        }
+    }
+
+    #[test]
+    fn test_activation_values() {
+        assert_eq!(Activation::LeakyReLU(0.2).activate(-2.0), -0.4);
+        assert_eq!(Activation::ELU(1.0).activate(2.0), 2.0);
+        assert!((Activation::ELU(1.0).activate(-1.0) - (-0.632_120_56)).abs() < 1e-6);
+        assert!((Activation::SELU.activate(1.0) - 1.050_701).abs() < 1e-6);
+        assert!((Activation::Softplus.activate(0.0) - 2.0f32.ln()).abs() < 1e-6);
+        assert!((Activation::Softplus.activate(100.0) - 100.0).abs() < 1e-4);
+        assert!((Activation::SiLU.activate(1.0) - 0.731_058_6).abs() < 1e-6);
+        assert!((Activation::GELU.activate(1.0) - 0.841_192).abs() < 1e-5);
+        assert!((Activation::Mish.activate(1.0) - 0.865_098_4).abs() < 1e-5);
+        assert_eq!(Activation::HardSigmoid.activate(0.0), 0.5);
+        assert_eq!(Activation::HardSigmoid.activate(4.0), 1.0);
+        assert_eq!(Activation::HardSigmoid.activate(-4.0), 0.0);
+    }
 }
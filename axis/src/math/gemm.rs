@@ -0,0 +1,143 @@
+//! Matrix multiplication kernels for the float types, used by [`Matrix::dot`] and its
+//! transposed forms.
+//!
+//! [`Matrix::dot`]: super::Matrix::dot
+
+use std::ops::{Add, AddAssign, Mul};
+
+/// Columns of the right hand side processed per block.
+const BLOCK_COLS: usize = 256;
+/// Rows of the right hand side, and columns of the left hand side, processed per block.
+const BLOCK_DEPTH: usize = 128;
+/// Products smaller than this many multiply-adds are not worth spreading across threads.
+#[cfg(feature = "parallel")]
+const PARALLEL_THRESHOLD: usize = 1 << 18;
+
+pub(crate) trait Float:
+    Copy + Default + Send + Sync + Add<Output = Self> + Mul<Output = Self> + AddAssign + 'static
+{
+}
+
+impl Float for f32 {}
+impl Float for f64 {}
+
+/// Computes `c = a·b` for a row-major `(m, k)` matrix `a` and `(k, n)` matrix `b`, where `c`
+/// holds `m * n` zeros. With the `parallel` feature, large products split the rows of `c`
+/// across threads.
+pub(crate) fn gemm<F: Float>(a: &[F], b: &[F], c: &mut [F], k: usize, n: usize) {
+    if k == 0 || n == 0 {
+        return;
+    }
+
+    split_rows(a, c, k, n, |a, c| blocked(a, b, c, k, n));
+}
+
+/// Computes `c = a·bᵀ` for a row-major `(m, k)` matrix `a` and `(n, k)` matrix `b`, where `c`
+/// holds `m * n` zeros, without transposing `b`.
+pub(crate) fn gemm_transposed<F: Float>(a: &[F], b: &[F], c: &mut [F], k: usize, n: usize) {
+    if k == 0 || n == 0 {
+        return;
+    }
+
+    split_rows(a, c, k, n, |a, c| {
+        for (a_row, c_row) in a.chunks_exact(k).zip(c.chunks_exact_mut(n)) {
+            for (c, b_row) in c_row.iter_mut().zip(b.chunks_exact(k)) {
+                *c += dot(a_row, b_row);
+            }
+        }
+    });
+}
+
+/// Computes `c = aᵀ·b` for a row-major `(k, m)` matrix `a` and `(k, n)` matrix `b`, where `c`
+/// holds `m * n` zeros, without transposing `a`.
+pub(crate) fn transpose_gemm<F: Float>(a: &[F], b: &[F], c: &mut [F], m: usize, n: usize) {
+    if m == 0 || n == 0 {
+        return;
+    }
+
+    for (a_row, b_row) in a.chunks_exact(m).zip(b.chunks_exact(n)) {
+        for (&scale, c_row) in a_row.iter().zip(c.chunks_exact_mut(n)) {
+            for (c, &b) in c_row.iter_mut().zip(b_row) {
+                *c += scale * b;
+            }
+        }
+    }
+}
+
+/// Runs `kernel` over the rows of the `(m, k)` matrix `a` and `(m, n)` matrix `c`. With the
+/// `parallel` feature, large products split the rows across threads.
+#[cfg_attr(not(feature = "parallel"), allow(unused_variables))]
+fn split_rows<F: Float>(
+    a: &[F],
+    c: &mut [F],
+    k: usize,
+    n: usize,
+    kernel: impl Fn(&[F], &mut [F]) + Sync,
+) {
+    #[cfg(feature = "parallel")]
+    {
+        let m = c.len() / n;
+        if m > 1 && m * k * n >= PARALLEL_THRESHOLD && threads() > 1 {
+            let rows = m.div_ceil(threads());
+            let kernel = &kernel;
+            std::thread::scope(|scope| {
+                for (a, c) in a.chunks(rows * k).zip(c.chunks_mut(rows * n)) {
+                    scope.spawn(move || kernel(a, c));
+                }
+            });
+            return;
+        }
+    }
+
+    kernel(a, c);
+}
+
+/// The dot product of two rows, summed in `LANES` independent accumulators so that it
+/// vectorizes.
+fn dot<F: Float>(a: &[F], b: &[F]) -> F {
+    const LANES: usize = 8;
+
+    let mut sums = [F::default(); LANES];
+    let (a_chunks, b_chunks) = (a.chunks_exact(LANES), b.chunks_exact(LANES));
+    let remainder = a_chunks
+        .remainder()
+        .iter()
+        .zip(b_chunks.remainder())
+        .fold(F::default(), |sum, (&a, &b)| sum + a * b);
+    for (a, b) in a_chunks.zip(b_chunks) {
+        for lane in 0..LANES {
+            sums[lane] += a[lane] * b[lane];
+        }
+    }
+
+    sums.into_iter().fold(remainder, |sum, x| sum + x)
+}
+
+/// The number of threads to split products across, queried once.
+#[cfg(feature = "parallel")]
+fn threads() -> usize {
+    static THREADS: std::sync::OnceLock<usize> = std::sync::OnceLock::new();
+    *THREADS.get_or_init(|| std::thread::available_parallelism().map_or(1, |n| n.get()))
+}
+
+/// Walks `b` in `BLOCK_DEPTH` by `BLOCK_COLS` tiles that stay in cache while every row of
+/// `a` is multiplied into them. The inner loop runs along contiguous rows of `b` and `c`, so
+/// it vectorizes.
+fn blocked<F: Float>(a: &[F], b: &[F], c: &mut [F], k: usize, n: usize) {
+    for depth in (0..k).step_by(BLOCK_DEPTH) {
+        let depth_end = (depth + BLOCK_DEPTH).min(k);
+        for col in (0..n).step_by(BLOCK_COLS) {
+            let col_end = (col + BLOCK_COLS).min(n);
+            for (a_row, c_row) in a.chunks_exact(k).zip(c.chunks_exact_mut(n)) {
+                let c_row = &mut c_row[col..col_end];
+                for p in depth..depth_end {
+                    let scale = a_row[p];
+                    let b_row = &b[p * n + col..p * n + col_end];
+                    for (c, &b) in c_row.iter_mut().zip(b_row) {
+                        *c += scale * b;
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use super::Matrix;
+use crate::domain::random_provider;
+
+/// Strategies for drawing the initial values of a parameter matrix. The variance scaling
+/// initializers take the layer's `fan_in` (number of inputs) and `fan_out` (number of
+/// outputs) into account.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Initializer {
+    Uniform(f32, f32),
+    Normal(f32, f32),
+    XavierUniform,
+    XavierNormal,
+    HeUniform,
+    HeNormal,
+    LeCunUniform,
+    LeCunNormal,
+    Zeros,
+    Constant(f32),
+    Orthogonal,
+}
+
+impl Initializer {
+    pub fn initialize(&self, shape: (usize, usize), fan_in: usize, fan_out: usize) -> Matrix<f32> {
+        let fan_in = fan_in.max(1) as f32;
+        let fan_out = fan_out.max(1) as f32;
+
+        match *self {
+            Initializer::Uniform(low, high) => Matrix::random(shape, low..high),
+            Initializer::Normal(mean, std_dev) => normal(shape, mean, std_dev),
+            Initializer::XavierUniform => uniform(shape, (6.0 / (fan_in + fan_out)).sqrt()),
+            Initializer::XavierNormal => normal(shape, 0.0, (2.0 / (fan_in + fan_out)).sqrt()),
+            Initializer::HeUniform => uniform(shape, (6.0 / fan_in).sqrt()),
+            Initializer::HeNormal => normal(shape, 0.0, (2.0 / fan_in).sqrt()),
+            Initializer::LeCunUniform => uniform(shape, (3.0 / fan_in).sqrt()),
+            Initializer::LeCunNormal => normal(shape, 0.0, (1.0 / fan_in).sqrt()),
+            Initializer::Zeros => Matrix::zeros(shape),
+            Initializer::Constant(value) => {
+                let mut matrix = Matrix::zeros(shape);
+                matrix.fill(value);
+                matrix
+            }
+            Initializer::Orthogonal => orthogonal(shape),
+        }
+    }
+}
+
+fn uniform(shape: (usize, usize), limit: f32) -> Matrix<f32> {
+    Matrix::random(shape, -limit..limit)
+}
+
+fn normal(shape: (usize, usize), mean: f32, std_dev: f32) -> Matrix<f32> {
+    let mut matrix = Matrix::zeros(shape);
+    for value in matrix.iter_mut() {
+        *value = random_provider::gaussian(mean as f64, std_dev as f64) as f32;
+    }
+
+    matrix
+}
+
+/// Draws a gaussian matrix and orthonormalizes the shorter dimension with Gram-Schmidt,
+/// so the rows (or columns, for tall matrices) form an orthonormal set.
+fn orthogonal(shape: (usize, usize)) -> Matrix<f32> {
+    let (rows, cols) = if shape.0 > shape.1 {
+        (shape.1, shape.0)
+    } else {
+        shape
+    };
+
+    let mut matrix = normal((rows, cols), 0.0, 1.0);
+    for i in 0..rows {
+        for j in 0..i {
+            let projection = (0..cols)
+                .map(|k| matrix[(i, k)] * matrix[(j, k)])
+                .sum::<f32>();
+            for k in 0..cols {
+                matrix[(i, k)] -= projection * matrix[(j, k)];
+            }
+        }
+
+        let norm = (0..cols)
+            .map(|k| matrix[(i, k)].powi(2))
+            .sum::<f32>()
+            .sqrt()
+            .max(f32::EPSILON);
+        for k in 0..cols {
+            matrix[(i, k)] /= norm;
+        }
+    }
+
+    if shape.0 > shape.1 {
+        matrix.transpose()
+    } else {
+        matrix
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn mean_and_std(matrix: &Matrix<f32>) -> (f32, f32) {
+        let n = matrix.len() as f32;
+        let mean = matrix.iter().sum::<f32>() / n;
+        let var = matrix.iter().map(|x| (x - mean).powi(2)).sum::<f32>() / n;
+        (mean, var.sqrt())
+    }
+
+    #[test]
+    fn test_uniform_initializers_bounds() {
+        random_provider::seed(42);
+
+        let xavier = Initializer::XavierUniform.initialize((64, 32), 32, 64);
+        let limit = (6.0f32 / 96.0).sqrt();
+        assert!(xavier.iter().all(|x| x.abs() <= limit));
+
+        let he = Initializer::HeUniform.initialize((64, 32), 32, 64);
+        let limit = (6.0f32 / 32.0).sqrt();
+        assert!(he.iter().all(|x| x.abs() <= limit));
+
+        let lecun = Initializer::LeCunUniform.initialize((64, 32), 32, 64);
+        let limit = (3.0f32 / 32.0).sqrt();
+        assert!(lecun.iter().all(|x| x.abs() <= limit));
+    }
+
+    #[test]
+    fn test_normal_initializers_std() {
+        random_provider::seed(42);
+
+        let (mean, std) = mean_and_std(&Initializer::HeNormal.initialize((200, 100), 100, 200));
+        assert!(mean.abs() < 0.01);
+        assert!((std - (2.0f32 / 100.0).sqrt()).abs() < 0.01);
+
+        let (mean, std) = mean_and_std(&Initializer::XavierNormal.initialize((200, 100), 100, 200));
+        assert!(mean.abs() < 0.01);
+        assert!((std - (2.0f32 / 300.0).sqrt()).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_constant_initializers() {
+        assert!(
+            Initializer::Zeros
+                .initialize((3, 2), 2, 3)
+                .iter()
+                .all(|&x| x == 0.0)
+        );
+        assert!(
+            Initializer::Constant(0.1)
+                .initialize((3, 2), 2, 3)
+                .iter()
+                .all(|&x| x == 0.1)
+        );
+    }
+
+    #[test]
+    fn test_orthogonal_initializer() {
+        random_provider::seed(42);
+
+        for shape in [(4, 4), (3, 8), (8, 3)] {
+            let matrix = Initializer::Orthogonal.initialize(shape, shape.1, shape.0);
+            assert_eq!(matrix.shape(), shape);
+
+            let gram = if shape.0 <= shape.1 {
+                matrix.dot(&matrix.transpose())
+            } else {
+                matrix.transpose().dot(&matrix)
+            };
+
+            for i in 0..gram.rows() {
+                for j in 0..gram.cols() {
+                    let expected = if i == j { 1.0 } else { 0.0 };
+                    assert!((gram[(i, j)] - expected).abs() < 1e-5);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_initializer_is_deterministic() {
+        let first =
+            random_provider::scoped_seed(7, || Initializer::HeNormal.initialize((4, 4), 4, 4));
+        let second =
+            random_provider::scoped_seed(7, || Initializer::HeNormal.initialize((4, 4), 4, 4));
+
+        assert_eq!(first, second);
+    }
+}
@@ -1,4 +1,7 @@
+use super::Matrix;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Loss {
     MSE,
     CrossEntropy,
@@ -8,55 +11,218 @@ pub enum Loss {
     Huber,
 }
 
+const EPSILON: f32 = 1e-7;
+
 impl Loss {
-    pub fn apply<T: AsRef<[f32]>>(&self, y_true: &T, y_pred: &T) -> Vec<f32> {
-        match self {
-            Loss::MSE => y_true
-                .as_ref()
-                .iter()
-                .zip(y_pred.as_ref().iter())
-                .map(|(y_t, y_p)| 2.0 * (y_p - y_t))
-                .collect(),
-            Loss::CrossEntropy => y_true
-                .as_ref()
-                .iter()
-                .zip(y_pred.as_ref().iter())
-                .map(|(y_t, y_p)| -y_t * y_p.ln() - (1.0 - y_t) * (1.0 - y_p).ln())
-                .collect(),
-            Loss::BinaryCrossEntropy => y_true
-                .as_ref()
-                .iter()
-                .zip(y_pred.as_ref().iter())
-                .map(|(y_t, y_p)| -y_t * y_p.ln() - (1.0 - y_t) * (1.0 - y_p).ln())
-                .collect(),
-            Loss::Difference => y_true
-                .as_ref()
-                .iter()
-                .zip(y_pred.as_ref().iter())
-                .map(|(y_t, y_p)| (y_t - y_p).abs())
-                .collect(),
-            Loss::Hinge => y_true
-                .as_ref()
-                .iter()
-                .zip(y_pred.as_ref().iter())
-                .map(|(y_t, y_p)| (1.0 - y_t * y_p).max(0.0))
-                .collect(),
-            Loss::Huber => {
-                let delta = 1.0;
-                y_true
-                    .as_ref()
-                    .iter()
-                    .zip(y_pred.as_ref().iter())
-                    .map(|(y_t, y_p)| {
-                        let error = (y_t - y_p).abs();
-                        if error <= delta {
-                            0.5 * error.powi(2)
-                        } else {
-                            delta * (error - 0.5 * delta)
-                        }
-                    })
-                    .collect()
-            }
+    /// Computes the loss of `y_pred` against `y_true`, whose rows are samples: the loss of
+    /// each sample is summed over its outputs, then averaged over the samples.
+    /// `CrossEntropy` is the categorical cross-entropy, so its per-output loss is
+    /// `-y_true * ln(y_pred)`; `BinaryCrossEntropy` also penalizes the negative class.
+    pub fn value(&self, y_true: &Matrix<f32>, y_pred: &Matrix<f32>) -> f32 {
+        check_shapes(y_true, y_pred);
+
+        if y_true.is_empty() {
+            return 0.0;
         }
+
+        let total = y_true
+            .iter()
+            .zip(y_pred.iter())
+            .map(|(y_t, y_p)| match self {
+                Loss::MSE => (y_p - y_t).powi(2),
+                Loss::CrossEntropy => -y_t * y_p.max(EPSILON).ln(),
+                Loss::BinaryCrossEntropy => {
+                    let y_p = y_p.clamp(EPSILON, 1.0 - EPSILON);
+                    -y_t * y_p.ln() - (1.0 - y_t) * (1.0 - y_p).ln()
+                }
+                Loss::Difference => (y_t - y_p).abs(),
+                Loss::Hinge => (1.0 - y_t * y_p).max(0.0),
+                Loss::Huber => huber(y_t - y_p),
+            })
+            .sum::<f32>();
+
+        total / y_true.rows() as f32
+    }
+
+    /// Computes the gradient of [`Loss::value`] with respect to every prediction, so each
+    /// output's derivative is divided by the number of samples. When `CrossEntropy` follows
+    /// a softmax output the perceptron skips this and uses the fused gradient
+    /// `(y_pred - y_true) / samples` with respect to the logits.
+    pub fn gradient(&self, y_true: &Matrix<f32>, y_pred: &Matrix<f32>) -> Matrix<f32> {
+        check_shapes(y_true, y_pred);
+
+        let scale = 1.0 / y_pred.rows().max(1) as f32;
+        let gradient = y_true
+            .iter()
+            .zip(y_pred.iter())
+            .map(|(y_t, y_p)| match self {
+                Loss::MSE => 2.0 * (y_p - y_t),
+                Loss::CrossEntropy => -y_t / y_p.max(EPSILON),
+                Loss::BinaryCrossEntropy => {
+                    let y_p = y_p.clamp(EPSILON, 1.0 - EPSILON);
+                    (y_p - y_t) / (y_p * (1.0 - y_p))
+                }
+                Loss::Difference => sign(y_p - y_t),
+                Loss::Hinge => {
+                    if y_t * y_p < 1.0 {
+                        -y_t
+                    } else {
+                        0.0
+                    }
+                }
+                Loss::Huber => huber_gradient(y_p - y_t),
+            })
+            .map(|gradient| gradient * scale)
+            .collect::<Vec<f32>>();
+
+        Matrix::from(gradient).reshape(y_pred.shape())
+    }
+}
+
+const HUBER_DELTA: f32 = 1.0;
+
+fn huber(error: f32) -> f32 {
+    let error = error.abs();
+    if error <= HUBER_DELTA {
+        0.5 * error.powi(2)
+    } else {
+        HUBER_DELTA * (error - 0.5 * HUBER_DELTA)
+    }
+}
+
+fn huber_gradient(error: f32) -> f32 {
+    if error.abs() <= HUBER_DELTA {
+        error
+    } else {
+        HUBER_DELTA * sign(error)
+    }
+}
+
+fn sign(x: f32) -> f32 {
+    if x > 0.0 {
+        1.0
+    } else if x < 0.0 {
+        -1.0
+    } else {
+        0.0
+    }
+}
+
+fn check_shapes(y_true: &Matrix<f32>, y_pred: &Matrix<f32>) {
+    if y_true.shape() != y_pred.shape() {
+        panic!(
+            "Loss targets of shape {:?} do not match predictions of shape {:?}",
+            y_true.shape(),
+            y_pred.shape()
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_loss_value() {
+        let y_true = Matrix::from(vec![1.0, 0.0]);
+        let y_pred = Matrix::from(vec![0.5, 0.5]);
+
+        assert_eq!(Loss::MSE.value(&y_true, &y_pred), 0.5);
+        assert_eq!(Loss::Difference.value(&y_true, &y_pred), 1.0);
+        assert_eq!(Loss::Huber.value(&y_true, &y_pred), 0.25);
+        assert_eq!(Loss::Hinge.value(&y_true, &y_pred), 1.5);
+        assert!(
+            (Loss::BinaryCrossEntropy.value(&y_true, &y_pred) - 2.0 * 2.0f32.ln()).abs() < 1e-6
+        );
+        assert!((Loss::CrossEntropy.value(&y_true, &y_pred) - 2.0f32.ln()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_loss_averages_over_samples() {
+        let y_true = Matrix::from(vec![1.0, 0.0, 0.0, 1.0, 0.0, 0.0]).reshape((2, 3));
+        let y_pred = Matrix::from(vec![0.5, 0.25, 0.25, 0.25, 0.5, 0.25]).reshape((2, 3));
+
+        let expected = -(0.5f32.ln() + 0.25f32.ln()) / 2.0;
+        assert!((Loss::CrossEntropy.value(&y_true, &y_pred) - expected).abs() < 1e-6);
+
+        let expected = (0.25 + 0.0625 + 0.0625 + 0.5625 + 0.25 + 0.0625) / 2.0;
+        assert!((Loss::MSE.value(&y_true, &y_pred) - expected).abs() < 1e-6);
+        assert_eq!(Loss::MSE.gradient(&y_true, &y_pred)[(1, 0)], -0.75);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Loss targets of shape (1, 2) do not match predictions of shape (2, 1)"
+    )]
+    fn test_loss_checks_shapes() {
+        let y_true = Matrix::from(vec![1.0, 0.0]);
+        Loss::MSE.value(&y_true, &y_true.clone().reshape((2, 1)));
+    }
+
+    fn gradient_check(loss: Loss, y_true: &[f32], y_pred: &[f32]) {
+        let epsilon = 1e-3;
+        let shape = (2, y_pred.len() / 2);
+        let y_true = Matrix::from(y_true.to_vec()).reshape(shape);
+        let y_pred = Matrix::from(y_pred.to_vec()).reshape(shape);
+        let analytic = loss.gradient(&y_true, &y_pred);
+
+        for i in 0..y_pred.len() {
+            let mut plus = y_pred.clone();
+            let mut minus = y_pred.clone();
+            plus.as_mut()[i] += epsilon;
+            minus.as_mut()[i] -= epsilon;
+
+            let numeric =
+                (loss.value(&y_true, &plus) - loss.value(&y_true, &minus)) / (2.0 * epsilon);
+            let analytic = analytic.as_ref()[i];
+
+            let error = (numeric - analytic).abs() / numeric.abs().max(analytic.abs()).max(1.0);
+            assert!(
+                error < 1e-2,
+                "{loss:?}: element {i} numeric {numeric} analytic {analytic}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_mse_gradient() {
+        gradient_check(Loss::MSE, &[1.0, 0.0, -2.0, 0.5], &[0.3, 0.8, 1.5, -0.4]);
+    }
+
+    #[test]
+    fn test_cross_entropy_gradient() {
+        gradient_check(
+            Loss::CrossEntropy,
+            &[1.0, 0.0, 0.0, 0.0, 1.0, 0.0],
+            &[0.3, 0.5, 0.2, 0.1, 0.6, 0.3],
+        );
+    }
+
+    #[test]
+    fn test_binary_cross_entropy_gradient() {
+        gradient_check(
+            Loss::BinaryCrossEntropy,
+            &[1.0, 0.0, 0.3, 1.0],
+            &[0.3, 0.8, 0.6, 0.9],
+        );
+    }
+
+    #[test]
+    fn test_difference_gradient() {
+        gradient_check(
+            Loss::Difference,
+            &[1.0, 0.0, -2.0, 0.5],
+            &[0.3, 0.8, 1.5, -0.4],
+        );
+    }
+
+    #[test]
+    fn test_hinge_gradient() {
+        gradient_check(Loss::Hinge, &[1.0, -1.0, 1.0, -1.0], &[0.3, 0.8, 1.5, -0.4]);
+    }
+
+    #[test]
+    fn test_huber_gradient() {
+        gradient_check(Loss::Huber, &[1.0, 0.0, -2.0, 0.5], &[0.3, 0.8, 1.5, -0.4]);
     }
 }
@@ -1,16 +1,57 @@
+use super::gemm;
+#[cfg(feature = "simd")]
+use super::simd;
 use crate::domain::random_provider;
 use rand::distr::StandardUniform;
 use rand::distr::uniform::SampleUniform;
 use rand::prelude::Distribution;
 use std::fmt::Debug;
-use std::ops::{Add, Div, Index, IndexMut, Mul, Range, Sub};
+use std::ops::{
+    Add, AddAssign, Div, DivAssign, Index, IndexMut, Mul, MulAssign, Range, Sub, SubAssign,
+};
 
 #[derive(Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(
+        try_from = "RawMatrix<T>",
+        bound(deserialize = "T: serde::Deserialize<'de>")
+    )
+)]
 pub struct Matrix<T> {
     data: Vec<T>,
     shape: (usize, usize),
 }
 
+/// The unchecked serialized form of a [`Matrix`], validated before it becomes one.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct RawMatrix<T> {
+    data: Vec<T>,
+    shape: (usize, usize),
+}
+
+#[cfg(feature = "serde")]
+impl<T> TryFrom<RawMatrix<T>> for Matrix<T> {
+    type Error = String;
+
+    fn try_from(raw: RawMatrix<T>) -> Result<Self, Self::Error> {
+        if raw.data.len() != raw.shape.0 * raw.shape.1 {
+            return Err(format!(
+                "Matrix of shape {:?} cannot hold {} values",
+                raw.shape,
+                raw.data.len()
+            ));
+        }
+
+        Ok(Matrix {
+            data: raw.data,
+            shape: raw.shape,
+        })
+    }
+}
+
 impl<T> Matrix<T> {
     pub fn new(rows: usize, cols: usize) -> Self
     where
@@ -111,6 +152,24 @@ impl<T> Matrix<T> {
         }
     }
 
+    pub fn vstack<'a>(matrices: impl IntoIterator<Item = &'a Matrix<T>>) -> Self
+    where
+        T: Clone + 'a,
+    {
+        let mut data = Vec::new();
+        let mut shape = (0, 0);
+        for (i, matrix) in matrices.into_iter().enumerate() {
+            if i > 0 && matrix.shape.1 != shape.1 {
+                panic!("Matrix dimensions do not match");
+            }
+
+            data.extend_from_slice(&matrix.data);
+            shape = (shape.0 + matrix.shape.0, matrix.shape.1);
+        }
+
+        Matrix { data, shape }
+    }
+
     pub fn fill(&mut self, value: T)
     where
         T: Default + Clone,
@@ -149,209 +208,311 @@ impl<T> Matrix<T> {
     }
 }
 
-impl<T> Matrix<T>
-where
-    T: Default + Clone + Add<Output = T> + Mul<Output = T>,
-{
+impl<T: Dot> Matrix<T> {
+    /// The matrix product. `f32` and `f64` matrices use a cache blocked kernel, which the
+    /// `parallel` feature spreads across threads for large products.
     pub fn dot(&self, other: &Matrix<T>) -> Matrix<T> {
         if self.shape.1 != other.shape.0 {
             panic!("Matrix dimensions do not match");
         }
 
-        let mut result = Matrix::new(self.shape.0, other.shape.1);
-        for i in 0..self.shape.0 {
-            for j in 0..other.shape.1 {
-                let mut sum = T::default();
-                for k in 0..self.shape.1 {
-                    sum = sum + self[(i, k)].clone() * other[(k, j)].clone();
-                }
+        let (inner, cols) = other.shape;
+        let mut result = Matrix::new(self.shape.0, cols);
+        T::gemm(&self.data, &other.data, &mut result.data, inner, cols);
+        result
+    }
 
-                result[(i, j)] = sum;
-            }
+    /// The product `self·otherᵀ`, without allocating the transpose of `other`.
+    pub fn dot_transposed(&self, other: &Matrix<T>) -> Matrix<T> {
+        if self.shape.1 != other.shape.1 {
+            panic!("Matrix dimensions do not match");
         }
 
+        let (rows, inner) = other.shape;
+        let mut result = Matrix::new(self.shape.0, rows);
+        T::gemm_transposed(&self.data, &other.data, &mut result.data, inner, rows);
         result
     }
-}
 
-impl<T> Add for Matrix<T>
-where
-    T: Add<Output = T> + Clone,
-{
-    type Output = Self;
-
-    fn add(self, other: Self) -> Self::Output {
-        if self.shape != other.shape {
+    /// The product `selfᵀ·other`, without allocating the transpose of `self`.
+    pub fn transpose_dot(&self, other: &Matrix<T>) -> Matrix<T> {
+        if self.shape.0 != other.shape.0 {
             panic!("Matrix dimensions do not match");
         }
 
-        let data = self
-            .data
-            .iter()
-            .zip(other.data.iter())
-            .map(|(a, b)| a.clone() + b.clone())
-            .collect();
-
-        Matrix {
-            data,
-            shape: self.shape,
-        }
+        let (rows, cols) = (self.shape.1, other.shape.1);
+        let mut result = Matrix::new(rows, cols);
+        T::transpose_gemm(&self.data, &other.data, &mut result.data, rows, cols);
+        result
     }
 }
 
-impl<T> Add<T> for Matrix<T>
-where
-    T: Add<Output = T> + Clone,
-{
-    type Output = Self;
+/// The element types of [`Matrix::dot`] and its transposed forms: the primitive integer
+/// and float types.
+pub trait Dot: sealed::Dot {}
+
+impl<T: sealed::Dot> Dot for T {}
+
+mod sealed {
+    use super::gemm;
+    use std::ops::{Add, Mul};
+
+    /// The product kernels of an element type, on row-major data where `c` starts out
+    /// zeroed. The defaults are plain loops, which the float types replace with [`gemm`].
+    pub trait Dot: Default + Clone + Add<Output = Self> + Mul<Output = Self> {
+        /// `c = a·b` for an `(m, k)` matrix `a` and `(k, n)` matrix `b`.
+        fn gemm(a: &[Self], b: &[Self], c: &mut [Self], k: usize, n: usize) {
+            for (row, output) in a.chunks_exact(k.max(1)).zip(c.chunks_exact_mut(n.max(1))) {
+                for (scale, b_row) in row.iter().zip(b.chunks_exact(n.max(1))) {
+                    for (output, value) in output.iter_mut().zip(b_row) {
+                        *output = output.clone() + scale.clone() * value.clone();
+                    }
+                }
+            }
+        }
 
-    fn add(self, scaler: T) -> Self::Output {
-        let data = self
-            .data
-            .iter()
-            .map(|a| a.clone() + scaler.clone())
-            .collect();
+        /// `c = a·bᵀ` for an `(m, k)` matrix `a` and `(n, k)` matrix `b`.
+        fn gemm_transposed(a: &[Self], b: &[Self], c: &mut [Self], k: usize, n: usize) {
+            for (row, output) in a.chunks_exact(k.max(1)).zip(c.chunks_exact_mut(n.max(1))) {
+                for (output, b_row) in output.iter_mut().zip(b.chunks_exact(k.max(1))) {
+                    for (a, b) in row.iter().zip(b_row) {
+                        *output = output.clone() + a.clone() * b.clone();
+                    }
+                }
+            }
+        }
 
-        Matrix {
-            data,
-            shape: self.shape,
+        /// `c = aᵀ·b` for a `(k, m)` matrix `a` and `(k, n)` matrix `b`.
+        fn transpose_gemm(a: &[Self], b: &[Self], c: &mut [Self], m: usize, n: usize) {
+            for (row, b_row) in a.chunks_exact(m.max(1)).zip(b.chunks_exact(n.max(1))) {
+                for (scale, output) in row.iter().zip(c.chunks_exact_mut(n.max(1))) {
+                    for (output, value) in output.iter_mut().zip(b_row) {
+                        *output = output.clone() + scale.clone() * value.clone();
+                    }
+                }
+            }
         }
     }
-}
 
-impl<T> Mul for Matrix<T>
-where
-    T: Add<Output = T> + Mul<Output = T> + Clone,
-{
-    type Output = Self;
+    macro_rules! float_dot {
+        ($($t:ty),*) => {$(
+            impl Dot for $t {
+                fn gemm(a: &[Self], b: &[Self], c: &mut [Self], k: usize, n: usize) {
+                    gemm::gemm(a, b, c, k, n);
+                }
 
-    fn mul(self, other: Self) -> Self::Output {
-        if self.shape != other.shape {
-            panic!("Matrix dimensions do not match");
-        }
+                fn gemm_transposed(a: &[Self], b: &[Self], c: &mut [Self], k: usize, n: usize) {
+                    gemm::gemm_transposed(a, b, c, k, n);
+                }
 
-        let data = self
-            .data
-            .iter()
-            .zip(other.data.iter())
-            .map(|(a, b)| a.clone() * b.clone())
-            .collect();
+                fn transpose_gemm(a: &[Self], b: &[Self], c: &mut [Self], m: usize, n: usize) {
+                    gemm::transpose_gemm(a, b, c, m, n);
+                }
+            }
+        )*};
+    }
 
-        Matrix {
-            data,
-            shape: self.shape,
-        }
+    float_dot!(f32, f64);
+
+    macro_rules! integer_dot {
+        ($($t:ty),*) => {$(impl Dot for $t {})*};
     }
+
+    integer_dot!(
+        i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize
+    );
 }
 
-impl<T> Mul<T> for Matrix<T>
-where
-    T: Mul<Output = T> + Clone,
-{
-    type Output = Self;
+/// Implements an elementwise operator: in place (`a += &b`, `a += 1.0`), by value
+/// (`a + b`), and borrowing (`&a + &b`). The in-place and by-value forms reuse the left
+/// hand side's buffer, so only the borrowing forms allocate. With the `simd` feature, the
+/// in-place loops go through the chunked kernels in [`simd`].
+macro_rules! elementwise {
+    ($Op:ident, $op:ident, $OpAssign:ident, $op_assign:ident, $symbol:tt) => {
+        impl<T> $OpAssign<&Matrix<T>> for Matrix<T>
+        where
+            T: $Op<Output = T> + Clone,
+        {
+            fn $op_assign(&mut self, other: &Matrix<T>) {
+                if self.shape != other.shape {
+                    let shape = broadcast_shape(self.shape, other.shape);
+                    if shape != self.shape {
+                        panic!(
+                            "Matrix of shape {:?} cannot be broadcast into shape {:?}",
+                            other.shape, self.shape
+                        );
+                    }
+
+                    // Walk whole rows, so a `(1, n)` row is zipped with every row of `self`
+                    // and a `(m, 1)` column stretches one value along its row.
+                    for (i, row) in self.data.chunks_exact_mut(shape.1.max(1)).enumerate() {
+                        let i = if other.shape.0 == 1 { 0 } else { i };
+                        let other_row = &other.data[i * other.shape.1..(i + 1) * other.shape.1];
+                        if let [b] = other_row {
+                            map_with(row, b, |a, b| a $symbol b);
+                        } else {
+                            zip_with(row, other_row, |a, b| a $symbol b);
+                        }
+                    }
+                    return;
+                }
 
-    fn mul(self, scaler: T) -> Self::Output {
-        let data = self
-            .data
-            .iter()
-            .map(|a| a.clone() * scaler.clone())
-            .collect();
+                zip_with(&mut self.data, &other.data, |a, b| a $symbol b);
+            }
+        }
 
-        Matrix {
-            data,
-            shape: self.shape,
+        impl<T> $OpAssign for Matrix<T>
+        where
+            T: $Op<Output = T> + Clone,
+        {
+            fn $op_assign(&mut self, other: Matrix<T>) {
+                $OpAssign::$op_assign(self, &other);
+            }
         }
-    }
-}
 
-impl<T> Div for Matrix<T>
-where
-    T: Div<Output = T> + Clone,
-{
-    type Output = Self;
+        impl<T> $OpAssign<T> for Matrix<T>
+        where
+            T: $Op<Output = T> + Clone,
+        {
+            fn $op_assign(&mut self, scaler: T) {
+                map_with(&mut self.data, &scaler, |a, b| a $symbol b);
+            }
+        }
 
-    fn div(self, other: Self) -> Self::Output {
-        if self.shape != other.shape {
-            panic!("Matrix dimensions do not match");
+        impl<T> $Op for Matrix<T>
+        where
+            T: $Op<Output = T> + Clone,
+        {
+            type Output = Self;
+
+            fn $op(self, other: Self) -> Self::Output {
+                self $symbol &other
+            }
         }
 
-        let data = self
-            .data
-            .iter()
-            .zip(other.data.iter())
-            .map(|(a, b)| a.clone() / b.clone())
-            .collect();
+        impl<T> $Op<&Matrix<T>> for Matrix<T>
+        where
+            T: $Op<Output = T> + Clone,
+        {
+            type Output = Self;
 
-        Matrix {
-            data,
-            shape: self.shape,
+            fn $op(mut self, other: &Matrix<T>) -> Self::Output {
+                if broadcast_shape(self.shape, other.shape) != self.shape {
+                    return broadcast(&self, other, |a, b| a $symbol b);
+                }
+
+                $OpAssign::$op_assign(&mut self, other);
+                self
+            }
         }
-    }
-}
 
-impl<T> Div<T> for Matrix<T>
-where
-    T: Div<Output = T> + Clone,
-{
-    type Output = Self;
+        impl<T> $Op<&Matrix<T>> for &Matrix<T>
+        where
+            T: $Op<Output = T> + Clone,
+        {
+            type Output = Matrix<T>;
 
-    fn div(self, scaler: T) -> Self::Output {
-        let data = self
-            .data
-            .iter()
-            .map(|a| a.clone() / scaler.clone())
-            .collect();
+            fn $op(self, other: &Matrix<T>) -> Self::Output {
+                if broadcast_shape(self.shape, other.shape) != self.shape {
+                    return broadcast(self, other, |a, b| a $symbol b);
+                }
 
-        Matrix {
-            data,
-            shape: self.shape,
+                self.clone() $symbol other
+            }
         }
-    }
-}
 
-impl<T> Sub for Matrix<T>
-where
-    T: Sub<Output = T> + Clone,
-{
-    type Output = Self;
+        impl<T> $Op<T> for Matrix<T>
+        where
+            T: $Op<Output = T> + Clone,
+        {
+            type Output = Self;
 
-    fn sub(self, other: Self) -> Self::Output {
-        if self.shape != other.shape {
-            panic!("Matrix dimensions do not match");
+            fn $op(mut self, scaler: T) -> Self::Output {
+                $OpAssign::$op_assign(&mut self, scaler);
+                self
+            }
         }
 
-        let data = self
-            .data
-            .iter()
-            .zip(other.data.iter())
-            .map(|(a, b)| a.clone() - b.clone())
-            .collect();
+        impl<T> $Op<T> for &Matrix<T>
+        where
+            T: $Op<Output = T> + Clone,
+        {
+            type Output = Matrix<T>;
 
-        Matrix {
-            data,
-            shape: self.shape,
+            fn $op(self, scaler: T) -> Self::Output {
+                self.clone() $symbol scaler
+            }
         }
+    };
+}
+
+elementwise!(Add, add, AddAssign, add_assign, +);
+elementwise!(Sub, sub, SubAssign, sub_assign, -);
+elementwise!(Mul, mul, MulAssign, mul_assign, *);
+elementwise!(Div, div, DivAssign, div_assign, /);
+
+/// Replaces every value of `a` with `op(a, b)` for the matching value of `b`.
+fn zip_with<T: Clone>(a: &mut [T], b: &[T], op: impl Fn(T, T) -> T) {
+    #[cfg(feature = "simd")]
+    simd::zip_with(a, b, op);
+    #[cfg(not(feature = "simd"))]
+    for (a, b) in a.iter_mut().zip(b) {
+        *a = op(a.clone(), b.clone());
     }
 }
 
-impl<T> Sub<T> for Matrix<T>
-where
-    T: Sub<Output = T> + Clone,
-{
-    type Output = Self;
+/// Replaces every value of `a` with `op(a, scalar)`.
+fn map_with<T: Clone>(a: &mut [T], scalar: &T, op: impl Fn(T, T) -> T) {
+    #[cfg(feature = "simd")]
+    simd::map_with(a, scalar, op);
+    #[cfg(not(feature = "simd"))]
+    for a in a.iter_mut() {
+        *a = op(a.clone(), scalar.clone());
+    }
+}
 
-    fn sub(self, scaler: T) -> Self::Output {
-        let data = self
-            .data
-            .iter()
-            .map(|a| a.clone() - scaler.clone())
-            .collect();
+/// The shape of an elementwise result between matrices of shapes `a` and `b`, where a
+/// dimension of 1 stretches to match the other, as in NumPy.
+fn broadcast_shape(a: (usize, usize), b: (usize, usize)) -> (usize, usize) {
+    let dim = |a: usize, b: usize| match (a, b) {
+        (a, b) if a == b || b == 1 => Some(a),
+        (1, b) => Some(b),
+        _ => None,
+    };
+
+    match (dim(a.0, b.0), dim(a.1, b.1)) {
+        (Some(rows), Some(cols)) => (rows, cols),
+        _ => panic!(
+            "Matrix shapes {:?} and {:?} cannot be broadcast together",
+            a, b
+        ),
+    }
+}
 
-        Matrix {
-            data,
-            shape: self.shape,
+/// Applies `op` to every pair of values of `a` and `b` after broadcasting them to a common
+/// shape.
+fn broadcast<T: Clone>(a: &Matrix<T>, b: &Matrix<T>, op: impl Fn(T, T) -> T) -> Matrix<T> {
+    let shape = broadcast_shape(a.shape, b.shape);
+    let mut data = Vec::with_capacity(shape.0 * shape.1);
+    for i in 0..shape.0 {
+        for j in 0..shape.1 {
+            data.push(op(
+                a.broadcast_at(i, j).clone(),
+                b.broadcast_at(i, j).clone(),
+            ));
         }
     }
+
+    Matrix { data, shape }
+}
+
+impl<T> Matrix<T> {
+    /// The value at `(i, j)` of this matrix stretched along its dimensions of size 1.
+    fn broadcast_at(&self, i: usize, j: usize) -> &T {
+        let i = if self.shape.0 == 1 { 0 } else { i };
+        let j = if self.shape.1 == 1 { 0 } else { j };
+        &self.data[i * self.shape.1 + j]
+    }
 }
 
 impl<T> Index<(usize, usize)> for Matrix<T> {
@@ -415,6 +576,7 @@ impl<T> Debug for Matrix<T>
 where
     T: Debug,
 {
+    #[allow(clippy::single_char_add_str)]
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         let mut s = String::new();
         for i in 0..self.shape.0 {
@@ -496,6 +658,133 @@ mod test {
         assert_eq!(result[(1, 1)], 4);
     }
 
+    #[test]
+    fn test_matrix_assign_operators() {
+        let mut matrix = Matrix::arange(0..4, 1).reshape((2, 2));
+        let other = Matrix::arange(4..8, 1).reshape((2, 2));
+
+        matrix += &other;
+        assert_eq!(matrix.as_ref(), &[4, 6, 8, 10]);
+        matrix -= 2;
+        assert_eq!(matrix.as_ref(), &[2, 4, 6, 8]);
+        matrix *= other.clone();
+        assert_eq!(matrix.as_ref(), &[8, 20, 36, 56]);
+        matrix /= 4;
+        assert_eq!(matrix.as_ref(), &[2, 5, 9, 14]);
+    }
+
+    #[test]
+    fn test_matrix_borrowing_operators() {
+        let matrix1 = Matrix::arange(1..5, 1).reshape((2, 2));
+        let matrix2 = Matrix::arange(5..9, 1).reshape((2, 2));
+
+        assert_eq!((&matrix1 + &matrix2).as_ref(), &[6, 8, 10, 12]);
+        assert_eq!((&matrix2 - &matrix1).as_ref(), &[4, 4, 4, 4]);
+        assert_eq!((&matrix1 * 3).as_ref(), &[3, 6, 9, 12]);
+        assert_eq!((matrix2.clone() / &matrix1).as_ref(), &[5, 3, 2, 2]);
+        assert_eq!(matrix1.as_ref(), &[1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_matrix_float_operators() {
+        let matrix1 = Matrix::<f32>::random((3, 7), 1.0..2.0);
+        let matrix2 = Matrix::<f64>::random((3, 7), 1.0..2.0);
+
+        let sum = &matrix1 + &matrix1;
+        let quotient = &matrix2 / &matrix2;
+        let mut scaled = matrix1.clone();
+        scaled *= 2.0;
+        let shifted = &matrix2 - 1.0;
+
+        for i in 0..3 {
+            for j in 0..7 {
+                assert_eq!(sum[(i, j)], matrix1[(i, j)] * 2.0);
+                assert_eq!(quotient[(i, j)], 1.0);
+                assert_eq!(scaled[(i, j)], sum[(i, j)]);
+                assert_eq!(shifted[(i, j)], matrix2[(i, j)] - 1.0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_matrix_operators_match_scalar_loop() {
+        // Runs with and without the `simd` feature, over rows that are not a whole number
+        // of its chunks.
+        let a = Matrix::<f64>::random((3, 19), 1.0..2.0);
+        let b = Matrix::<f64>::random((3, 19), 1.0..2.0);
+        let row = Matrix::<f64>::random((1, 19), 1.0..2.0);
+        let column = Matrix::<f64>::random((3, 1), 1.0..2.0);
+        let ops: [fn(f64, f64) -> f64; 4] =
+            [|a, b| a + b, |a, b| a - b, |a, b| a * b, |a, b| a / b];
+        let results = [
+            [&a + &b, &a + &row, &a + &column, &a + 0.5],
+            [&a - &b, &a - &row, &a - &column, &a - 0.5],
+            [&a * &b, &a * &row, &a * &column, &a * 0.5],
+            [&a / &b, &a / &row, &a / &column, &a / 0.5],
+        ];
+
+        for (op, [zipped, rows, columns, scaled]) in ops.iter().zip(&results) {
+            for i in 0..3 {
+                for j in 0..19 {
+                    assert_eq!(zipped[(i, j)], op(a[(i, j)], b[(i, j)]));
+                    assert_eq!(rows[(i, j)], op(a[(i, j)], row[(0, j)]));
+                    assert_eq!(columns[(i, j)], op(a[(i, j)], column[(i, 0)]));
+                    assert_eq!(scaled[(i, j)], op(a[(i, j)], 0.5));
+                }
+            }
+        }
+
+        let mut single = Matrix::<f32>::from(vec![1.0, 2.0, 3.0]);
+        single -= 1.0;
+        single *= &Matrix::from(vec![2.0, 3.0, 4.0]);
+        assert_eq!(single.as_ref(), &[0.0, 3.0, 8.0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Matrix shapes (2, 2) and (2, 3) cannot be broadcast together")]
+    fn test_matrix_assign_shape_mismatch() {
+        let mut matrix = Matrix::<f32>::zeros((2, 2));
+        matrix += &Matrix::zeros((2, 3));
+    }
+
+    #[test]
+    fn test_matrix_broadcasting() {
+        let batch = Matrix::arange(0..6, 1).reshape((2, 3));
+        let row = Matrix::from(vec![10, 20, 30]).reshape((1, 3));
+        let column = Matrix::from(vec![1, 2]).reshape((2, 1));
+
+        let expected = Matrix::from(vec![10, 21, 32, 13, 24, 35]).reshape((2, 3));
+        assert_eq!(&batch + &row, expected);
+        assert_eq!(&row + &batch, expected);
+        assert_eq!(batch.clone() + row.clone(), expected);
+
+        let mut scaled = batch.clone();
+        scaled *= &column;
+        assert_eq!(
+            scaled,
+            Matrix::from(vec![0, 1, 2, 6, 8, 10]).reshape((2, 3))
+        );
+        assert_eq!(
+            &row - &column,
+            Matrix::from(vec![9, 19, 29, 8, 18, 28]).reshape((2, 3))
+        );
+        assert_eq!(
+            &row / &Matrix::from(vec![10]).reshape((1, 1)),
+            Matrix::from(vec![1, 2, 3]).reshape((1, 3))
+        );
+        assert_eq!(
+            batch - &Matrix::from(vec![1]).reshape((1, 1)),
+            Matrix::from(vec![-1, 0, 1, 2, 3, 4]).reshape((2, 3))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Matrix of shape (2, 3) cannot be broadcast into shape (1, 3)")]
+    fn test_matrix_assign_broadcast_into_smaller() {
+        let mut row = Matrix::<f32>::zeros((1, 3));
+        row += &Matrix::zeros((2, 3));
+    }
+
     #[test]
     fn test_matrix_mul_matrix() {
         let matrix1 = Matrix::arange(1..5, 1).reshape((2, 2));
@@ -578,6 +867,82 @@ mod test {
         assert_eq!(result[(1, 1)], 50);
     }
 
+    #[test]
+    fn test_matrix_dot_blocked() {
+        // Large enough to span several blocks, and to run on several threads with the
+        // `parallel` feature, with sizes that do not divide evenly into blocks. Small
+        // integers multiply exactly in every type, so the float kernels must match the
+        // generic loop used for integers.
+        let a = Matrix::<i64>::random((70, 300), -8..8);
+        let b = Matrix::<i64>::random((300, 260), -8..8);
+        let expected = a.dot(&b);
+
+        let as_f32 = |m: &Matrix<i64>| Matrix {
+            data: m.data.iter().map(|&x| x as f32).collect(),
+            shape: m.shape,
+        };
+        let as_f64 = |m: &Matrix<i64>| Matrix {
+            data: m.data.iter().map(|&x| x as f64).collect(),
+            shape: m.shape,
+        };
+
+        assert_eq!(
+            expected[(0, 0)],
+            (0..300).map(|k| a[(0, k)] * b[(k, 0)]).sum::<i64>()
+        );
+        assert_eq!(as_f32(&a).dot(&as_f32(&b)), as_f32(&expected));
+        assert_eq!(as_f64(&a).dot(&as_f64(&b)), as_f64(&expected));
+    }
+
+    #[test]
+    fn test_matrix_dot_transposed() {
+        let a = Matrix::<i64>::random((5, 19), -8..8);
+        let b = Matrix::<i64>::random((7, 19), -8..8);
+        let c = Matrix::<i64>::random((5, 3), -8..8);
+        let as_f32 = |m: &Matrix<i64>| Matrix {
+            data: m.data.iter().map(|&x| x as f32).collect(),
+            shape: m.shape,
+        };
+
+        let expected = a.dot(&b.transpose());
+        assert_eq!(a.dot_transposed(&b), expected);
+        assert_eq!(as_f32(&a).dot_transposed(&as_f32(&b)), as_f32(&expected));
+
+        let expected = a.transpose().dot(&c);
+        assert_eq!(a.transpose_dot(&c), expected);
+        assert_eq!(as_f32(&a).transpose_dot(&as_f32(&c)), as_f32(&expected));
+    }
+
+    #[test]
+    fn test_matrix_dot_empty_inner_dimension() {
+        let result = Matrix::<f32>::new(2, 0).dot(&Matrix::new(0, 3));
+        assert_eq!(result, Matrix::zeros((2, 3)));
+    }
+
+    #[test]
+    fn test_matrix_vstack() {
+        let matrix1 = Matrix::arange(1..5, 1).reshape((2, 2));
+        let matrix2 = Matrix::arange(5..7, 1);
+
+        let result = Matrix::vstack([&matrix1, &matrix2]);
+
+        assert_eq!(result.shape(), (3, 2));
+        assert_eq!(result[(0, 0)], 1);
+        assert_eq!(result[(1, 1)], 4);
+        assert_eq!(result[(2, 0)], 5);
+        assert_eq!(result[(2, 1)], 6);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_matrix_serde() {
+        let matrix = Matrix::arange(0.0..6.0, 1.0).reshape((2, 3));
+        let json = serde_json::to_string(&matrix).unwrap();
+
+        assert_eq!(serde_json::from_str::<Matrix<f32>>(&json).unwrap(), matrix);
+        assert!(serde_json::from_str::<Matrix<f32>>(r#"{"data":[1.0],"shape":[2,2]}"#).is_err());
+    }
+
     #[test]
     fn test_matrix_transpose() {
         let result = Matrix::arange(1..7, 1).reshape((2, 3)).transpose();
@@ -1,13 +1,18 @@
 pub mod activation;
+mod gemm;
+pub mod initializer;
 pub mod loss;
 pub mod matrix;
 pub mod optimizer;
 pub mod shape;
+#[cfg(feature = "simd")]
+mod simd;
 pub mod tensor;
 
 pub use activation::Activation;
+pub use initializer::Initializer;
 pub use loss::*;
-pub use matrix::Matrix;
-pub use optimizer::Optimizer;
+pub use matrix::{Dot, Matrix};
+pub use optimizer::{Optimizer, OptimizerState};
 pub use shape::*;
 pub use tensor::*;
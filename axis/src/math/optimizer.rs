@@ -1,29 +1,465 @@
 use super::Matrix;
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Optimizer {
     SGD(f32),
+    Momentum {
+        learning_rate: f32,
+        momentum: f32,
+    },
+    Nesterov {
+        learning_rate: f32,
+        momentum: f32,
+    },
+    RMSProp {
+        learning_rate: f32,
+        decay: f32,
+        epsilon: f32,
+    },
+    AdaGrad {
+        learning_rate: f32,
+        epsilon: f32,
+    },
     Adam {
         learning_rate: f32,
         beta1: f32,
         beta2: f32,
         epsilon: f32,
     },
+    AdamW {
+        learning_rate: f32,
+        beta1: f32,
+        beta2: f32,
+        epsilon: f32,
+        weight_decay: f32,
+    },
 }
 
 impl Optimizer {
-    pub fn update(&self, weights: &mut Matrix<f32>, gradients: &mut Matrix<f32>) {
-        match self {
+    pub fn momentum(learning_rate: f32, momentum: f32) -> Self {
+        Optimizer::Momentum {
+            learning_rate,
+            momentum,
+        }
+    }
+
+    pub fn nesterov(learning_rate: f32, momentum: f32) -> Self {
+        Optimizer::Nesterov {
+            learning_rate,
+            momentum,
+        }
+    }
+
+    /// Creates an RMSProp optimizer with `decay = 0.9` and `epsilon = 1e-8`.
+    pub fn rmsprop(learning_rate: f32) -> Self {
+        Optimizer::RMSProp {
+            learning_rate,
+            decay: 0.9,
+            epsilon: 1e-8,
+        }
+    }
+
+    /// Creates an AdaGrad optimizer with `epsilon = 1e-8`.
+    pub fn adagrad(learning_rate: f32) -> Self {
+        Optimizer::AdaGrad {
+            learning_rate,
+            epsilon: 1e-8,
+        }
+    }
+
+    /// Creates an Adam optimizer with the commonly used defaults for the moment decay
+    /// rates (`beta1 = 0.9`, `beta2 = 0.999`) and `epsilon = 1e-8`.
+    pub fn adam(learning_rate: f32) -> Self {
+        Optimizer::Adam {
+            learning_rate,
+            beta1: 0.9,
+            beta2: 0.999,
+            epsilon: 1e-8,
+        }
+    }
+
+    /// Creates an AdamW optimizer with the same defaults as [`Optimizer::adam`]. The weight
+    /// decay is decoupled from the gradient, so it shrinks the weights directly instead of
+    /// being folded into the moment estimates.
+    pub fn adamw(learning_rate: f32, weight_decay: f32) -> Self {
+        Optimizer::AdamW {
+            learning_rate,
+            beta1: 0.9,
+            beta2: 0.999,
+            epsilon: 1e-8,
+            weight_decay,
+        }
+    }
+
+    /// Applies the accumulated `gradients` to `weights` and resets the gradients to zero.
+    /// The `state` holds whatever the optimizer needs to carry between steps for this
+    /// specific parameter matrix, so each weight or bias matrix must own its own state.
+    pub fn update(
+        &self,
+        state: &mut OptimizerState,
+        weights: &mut Matrix<f32>,
+        gradients: &mut Matrix<f32>,
+    ) {
+        state.step += 1;
+
+        match *self {
             Optimizer::SGD(learning_rate) => {
-                for i in 0..weights.rows() {
-                    for j in 0..weights.cols() {
-                        weights[(i, j)] -= learning_rate * gradients[(i, j)];
-                        gradients[(i, j)] = 0.0;
-                    }
+                for (weight, gradient) in weights.iter_mut().zip(gradients.iter()) {
+                    *weight -= learning_rate * gradient;
                 }
             }
-            Optimizer::Adam { .. } => {
-                panic!("Adam optimizer not implemented yet");
+            Optimizer::Momentum {
+                learning_rate,
+                momentum,
+            } => {
+                let [velocity] = state.moments_mut(weights.shape());
+                let params = weights
+                    .iter_mut()
+                    .zip(gradients.iter())
+                    .zip(velocity.iter_mut());
+
+                for ((weight, gradient), v) in params {
+                    *v = momentum * *v + gradient;
+                    *weight -= learning_rate * *v;
+                }
             }
+            Optimizer::Nesterov {
+                learning_rate,
+                momentum,
+            } => {
+                let [velocity] = state.moments_mut(weights.shape());
+                let params = weights
+                    .iter_mut()
+                    .zip(gradients.iter())
+                    .zip(velocity.iter_mut());
+
+                for ((weight, gradient), v) in params {
+                    *v = momentum * *v + gradient;
+                    *weight -= learning_rate * (gradient + momentum * *v);
+                }
+            }
+            Optimizer::RMSProp {
+                learning_rate,
+                decay,
+                epsilon,
+            } => {
+                let [square_avg] = state.moments_mut(weights.shape());
+                let params = weights
+                    .iter_mut()
+                    .zip(gradients.iter())
+                    .zip(square_avg.iter_mut());
+
+                for ((weight, gradient), s) in params {
+                    *s = decay * *s + (1.0 - decay) * gradient * gradient;
+                    *weight -= learning_rate * gradient / (s.sqrt() + epsilon);
+                }
+            }
+            Optimizer::AdaGrad {
+                learning_rate,
+                epsilon,
+            } => {
+                let [square_sum] = state.moments_mut(weights.shape());
+                let params = weights
+                    .iter_mut()
+                    .zip(gradients.iter())
+                    .zip(square_sum.iter_mut());
+
+                for ((weight, gradient), s) in params {
+                    *s += gradient * gradient;
+                    *weight -= learning_rate * gradient / (s.sqrt() + epsilon);
+                }
+            }
+            Optimizer::Adam {
+                learning_rate,
+                beta1,
+                beta2,
+                epsilon,
+            } => {
+                adam_step(
+                    state,
+                    weights,
+                    gradients,
+                    learning_rate,
+                    beta1,
+                    beta2,
+                    epsilon,
+                );
+            }
+            Optimizer::AdamW {
+                learning_rate,
+                beta1,
+                beta2,
+                epsilon,
+                weight_decay,
+            } => {
+                for weight in weights.iter_mut() {
+                    *weight -= learning_rate * weight_decay * *weight;
+                }
+
+                adam_step(
+                    state,
+                    weights,
+                    gradients,
+                    learning_rate,
+                    beta1,
+                    beta2,
+                    epsilon,
+                );
+            }
+        }
+
+        gradients.fill(0.0);
+    }
+}
+
+fn adam_step(
+    state: &mut OptimizerState,
+    weights: &mut Matrix<f32>,
+    gradients: &Matrix<f32>,
+    learning_rate: f32,
+    beta1: f32,
+    beta2: f32,
+    epsilon: f32,
+) {
+    let step = state.step as i32;
+    let [first, second] = state.moments_mut(weights.shape());
+
+    let first_correction = 1.0 - beta1.powi(step);
+    let second_correction = 1.0 - beta2.powi(step);
+
+    let params = weights
+        .iter_mut()
+        .zip(gradients.iter())
+        .zip(first.iter_mut().zip(second.iter_mut()));
+
+    for ((weight, gradient), (m, v)) in params {
+        *m = beta1 * *m + (1.0 - beta1) * gradient;
+        *v = beta2 * *v + (1.0 - beta2) * gradient * gradient;
+
+        let m_hat = *m / first_correction;
+        let v_hat = *v / second_correction;
+
+        *weight -= learning_rate * m_hat / (v_hat.sqrt() + epsilon);
+    }
+}
+
+/// Per-parameter state carried by an [`Optimizer`] across calls to [`Optimizer::update`].
+/// The moment buffers are allocated lazily on the first update so that stateless
+/// optimizers such as `SGD` never pay for them.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OptimizerState {
+    step: usize,
+    moments: Vec<Matrix<f32>>,
+}
+
+impl OptimizerState {
+    pub fn new() -> Self {
+        OptimizerState::default()
+    }
+
+    pub fn step(&self) -> usize {
+        self.step
+    }
+
+    /// The moment buffers, in the order the optimizer uses them. Empty until the first
+    /// update of a stateful optimizer.
+    pub fn moments(&self) -> &[Matrix<f32>] {
+        &self.moments
+    }
+
+    /// Rebuilds a state from a step count and its moment buffers, e.g. when loading a
+    /// saved model.
+    pub fn from_parts(step: usize, moments: Vec<Matrix<f32>>) -> Self {
+        OptimizerState { step, moments }
+    }
+
+    pub fn reset(&mut self) {
+        self.step = 0;
+        self.moments.clear();
+    }
+
+    fn moments_mut<const N: usize>(&mut self, shape: (usize, usize)) -> &mut [Matrix<f32>; N] {
+        if self.moments.len() != N || self.moments.iter().any(|m| m.shape() != shape) {
+            self.moments = (0..N).map(|_| Matrix::zeros(shape)).collect();
+        }
+
+        self.moments.as_mut_slice().try_into().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sgd_update() {
+        let mut state = OptimizerState::new();
+        let mut weights = Matrix::from(vec![1.0, 2.0]);
+        let mut gradients = Matrix::from(vec![0.5, -1.0]);
+
+        Optimizer::SGD(0.1).update(&mut state, &mut weights, &mut gradients);
+
+        assert_eq!(weights, Matrix::from(vec![0.95, 2.1]));
+        assert_eq!(gradients, Matrix::from(vec![0.0, 0.0]));
+    }
+
+    #[test]
+    fn test_adam_update() {
+        let optimizer = Optimizer::adam(0.1);
+        let mut state = OptimizerState::new();
+        let mut weights = Matrix::from(vec![1.0, 2.0]);
+
+        // With bias correction the first Adam step moves every weight by roughly the
+        // learning rate in the direction opposite to the gradient's sign.
+        let mut gradients = Matrix::from(vec![0.5, -4.0]);
+        optimizer.update(&mut state, &mut weights, &mut gradients);
+
+        assert_eq!(state.step(), 1);
+        assert!((weights[(0, 0)] - 0.9).abs() < 1e-5);
+        assert!((weights[(0, 1)] - 2.1).abs() < 1e-5);
+
+        let mut gradients = Matrix::from(vec![0.5, -4.0]);
+        optimizer.update(&mut state, &mut weights, &mut gradients);
+
+        assert_eq!(state.step(), 2);
+        assert!((weights[(0, 0)] - 0.8).abs() < 1e-5);
+        assert!((weights[(0, 1)] - 2.2).abs() < 1e-5);
+    }
+
+    fn assert_close(actual: &Matrix<f32>, expected: &[f32]) {
+        for (a, e) in actual.iter().zip(expected.iter()) {
+            assert!(
+                (a - e).abs() < 1e-5,
+                "expected {expected:?}, got {actual:?}"
+            );
         }
     }
+
+    #[test]
+    fn test_momentum_update() {
+        let optimizer = Optimizer::momentum(0.1, 0.9);
+        let mut state = OptimizerState::new();
+        let mut weights = Matrix::from(vec![1.0, 2.0]);
+
+        // v = g, w = w - 0.1 * v
+        let mut gradients = Matrix::from(vec![0.5, -1.0]);
+        optimizer.update(&mut state, &mut weights, &mut gradients);
+        assert_close(&weights, &[0.95, 2.1]);
+
+        // v = 0.9 * v + g = [0.95, -1.9], w = w - 0.1 * v
+        let mut gradients = Matrix::from(vec![0.5, -1.0]);
+        optimizer.update(&mut state, &mut weights, &mut gradients);
+        assert_close(&weights, &[0.855, 2.29]);
+    }
+
+    #[test]
+    fn test_nesterov_update() {
+        let optimizer = Optimizer::nesterov(0.1, 0.9);
+        let mut state = OptimizerState::new();
+        let mut weights = Matrix::from(vec![1.0, 2.0]);
+
+        // v = g = [0.5, -1.0], w = w - 0.1 * (g + 0.9 * v)
+        let mut gradients = Matrix::from(vec![0.5, -1.0]);
+        optimizer.update(&mut state, &mut weights, &mut gradients);
+        assert_close(&weights, &[0.905, 2.19]);
+
+        // v = [0.95, -1.9], w = w - 0.1 * (g + 0.9 * v)
+        let mut gradients = Matrix::from(vec![0.5, -1.0]);
+        optimizer.update(&mut state, &mut weights, &mut gradients);
+        assert_close(&weights, &[0.7695, 2.461]);
+    }
+
+    #[test]
+    fn test_rmsprop_update() {
+        let optimizer = Optimizer::RMSProp {
+            learning_rate: 0.1,
+            decay: 0.9,
+            epsilon: 0.0,
+        };
+        let mut state = OptimizerState::new();
+        let mut weights = Matrix::from(vec![1.0, 2.0]);
+
+        // s = 0.1 * g^2 = [0.1, 1.6], w = w - 0.1 * g / sqrt(s)
+        let mut gradients = Matrix::from(vec![1.0, -4.0]);
+        optimizer.update(&mut state, &mut weights, &mut gradients);
+        assert_close(&weights, &[0.6837722, 2.316228]);
+
+        // s = 0.9 * s + 0.1 * g^2 = [0.19, 3.04]
+        let mut gradients = Matrix::from(vec![1.0, -4.0]);
+        optimizer.update(&mut state, &mut weights, &mut gradients);
+        assert_close(&weights, &[0.4543587, 2.5456413]);
+    }
+
+    #[test]
+    fn test_adagrad_update() {
+        let optimizer = Optimizer::AdaGrad {
+            learning_rate: 0.1,
+            epsilon: 0.0,
+        };
+        let mut state = OptimizerState::new();
+        let mut weights = Matrix::from(vec![1.0, 2.0]);
+
+        // s = g^2 = [1, 16], w = w - 0.1 * g / sqrt(s)
+        let mut gradients = Matrix::from(vec![1.0, -4.0]);
+        optimizer.update(&mut state, &mut weights, &mut gradients);
+        assert_close(&weights, &[0.9, 2.1]);
+
+        // s = [2, 32], w = w - 0.1 * g / sqrt(s)
+        let mut gradients = Matrix::from(vec![1.0, -4.0]);
+        optimizer.update(&mut state, &mut weights, &mut gradients);
+        assert_close(&weights, &[0.8292893, 2.1707107]);
+    }
+
+    #[test]
+    fn test_adamw_update() {
+        let optimizer = Optimizer::adamw(0.1, 0.5);
+        let mut state = OptimizerState::new();
+        let mut weights = Matrix::from(vec![1.0, 2.0]);
+
+        // w = w - 0.1 * 0.5 * w = [0.95, 1.9], then the first Adam step moves each weight
+        // by the learning rate against the gradient's sign.
+        let mut gradients = Matrix::from(vec![0.5, -4.0]);
+        optimizer.update(&mut state, &mut weights, &mut gradients);
+        assert_close(&weights, &[0.85, 2.0]);
+    }
+
+    #[test]
+    fn test_optimizer_state_per_parameter() {
+        let optimizer = Optimizer::momentum(0.1, 0.9);
+        let mut weight_state = OptimizerState::new();
+        let mut bias_state = OptimizerState::new();
+        let mut weights = Matrix::from(vec![vec![1.0, 2.0], vec![3.0, 4.0]]);
+        let mut biases = Matrix::from(vec![1.0]);
+
+        optimizer.update(&mut weight_state, &mut weights, &mut Matrix::ones((2, 2)));
+        optimizer.update(&mut bias_state, &mut biases, &mut Matrix::ones((1, 1)));
+        optimizer.update(&mut weight_state, &mut weights, &mut Matrix::ones((2, 2)));
+
+        assert_eq!(weight_state.step(), 2);
+        assert_eq!(bias_state.step(), 1);
+        assert_close(&weights, &[0.71, 1.71, 2.71, 3.71]);
+        assert_close(&biases, &[0.9]);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_optimizer_serde() {
+        let optimizer = Optimizer::adamw(0.01, 0.1);
+        let json = serde_json::to_string(&optimizer).unwrap();
+        assert_eq!(serde_json::from_str::<Optimizer>(&json).unwrap(), optimizer);
+
+        let mut state = OptimizerState::new();
+        optimizer.update(
+            &mut state,
+            &mut Matrix::ones((2, 2)),
+            &mut Matrix::ones((2, 2)),
+        );
+        let json = serde_json::to_string(&state).unwrap();
+        assert_eq!(
+            serde_json::from_str::<OptimizerState>(&json).unwrap(),
+            state
+        );
+    }
 }
@@ -1,4 +1,5 @@
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Shape {
     pub dims: Vec<usize>,
 }
@@ -43,38 +44,65 @@ impl Shape {
     pub fn is_square(&self) -> bool {
         self.dims.len() == 2 && self.dims[0] == self.dims[1]
     }
+
+    /// The shape of an elementwise result between tensors of this shape and `other`, following
+    /// NumPy's rules: dimensions are aligned from the right, and each pair must be equal or
+    /// contain a 1, which stretches to match. `None` if the shapes are incompatible.
+    pub fn broadcast(&self, other: &Shape) -> Option<Shape> {
+        let rank = self.rank().max(other.rank());
+        let aligned = |shape: &Shape, i: usize| {
+            (i + shape.rank())
+                .checked_sub(rank)
+                .map_or(1, |i| shape.dim(i))
+        };
+
+        (0..rank)
+            .map(|i| match (aligned(self, i), aligned(other, i)) {
+                (a, b) if a == b || b == 1 => Some(a),
+                (1, b) => Some(b),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()
+            .map(Shape::new)
+    }
 }
 
+#[allow(clippy::from_over_into)]
 impl Into<Shape> for Vec<usize> {
     fn into(self) -> Shape {
         Shape::new(self)
     }
 }
 
+#[allow(clippy::from_over_into)]
 impl Into<Shape> for usize {
     fn into(self) -> Shape {
         Shape::new(vec![self])
     }
 }
 
+#[allow(clippy::from_over_into)]
 impl Into<Shape> for (usize, usize) {
     fn into(self) -> Shape {
         Shape::new(vec![self.0, self.1])
     }
 }
 
+#[allow(clippy::from_over_into)]
 impl Into<Shape> for (usize, usize, usize) {
     fn into(self) -> Shape {
         Shape::new(vec![self.0, self.1, self.2])
     }
 }
 
+#[allow(clippy::from_over_into)]
 impl Into<Shape> for (usize, usize, usize, usize) {
     fn into(self) -> Shape {
         Shape::new(vec![self.0, self.1, self.2, self.3])
     }
 }
 
+#[allow(clippy::from_over_into)]
 impl Into<Shape> for (usize, usize, usize, usize, usize) {
     fn into(self) -> Shape {
         Shape::new(vec![self.0, self.1, self.2, self.3, self.4])
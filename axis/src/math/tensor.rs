@@ -1,33 +1,318 @@
-use std::ops::{Index, IndexMut};
+use std::ops::{Add, AddAssign, Div, DivAssign, Index, IndexMut, Mul, MulAssign, Sub, SubAssign};
 
-use super::Shape;
+use super::{Matrix, Shape};
 
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(
+        try_from = "RawTensor<T>",
+        bound(deserialize = "T: serde::Deserialize<'de>")
+    )
+)]
 pub struct Tensor<T> {
     pub data: Vec<T>,
     pub shape: Shape,
     pub strides: Vec<usize>,
 }
 
+/// The unchecked serialized form of a [`Tensor`], validated before it becomes one.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct RawTensor<T> {
+    data: Vec<T>,
+    shape: Shape,
+    strides: Vec<usize>,
+}
+
+#[cfg(feature = "serde")]
+impl<T> TryFrom<RawTensor<T>> for Tensor<T> {
+    type Error = String;
+
+    fn try_from(raw: RawTensor<T>) -> Result<Self, Self::Error> {
+        if raw.data.len() != raw.shape.size() {
+            return Err(format!(
+                "Tensor of shape {:?} cannot hold {} values",
+                raw.shape.dims,
+                raw.data.len()
+            ));
+        }
+
+        if raw.strides.len() != raw.shape.rank() {
+            return Err(format!(
+                "Tensor of rank {} cannot have {} strides",
+                raw.shape.rank(),
+                raw.strides.len()
+            ));
+        }
+
+        // The furthest element the strides reach must lie inside the data, or indexing
+        // would read out of bounds.
+        let last = raw.shape.dims.iter().zip(raw.strides.iter()).try_fold(
+            0usize,
+            |offset, (&dim, &stride)| {
+                dim.saturating_sub(1)
+                    .checked_mul(stride)
+                    .and_then(|reach| offset.checked_add(reach))
+            },
+        );
+        if raw.shape.size() > 0 && last.is_none_or(|last| last >= raw.data.len()) {
+            return Err(format!(
+                "Tensor strides {:?} reach past the {} values of shape {:?}",
+                raw.strides,
+                raw.data.len(),
+                raw.shape.dims
+            ));
+        }
+
+        Ok(Tensor {
+            data: raw.data,
+            shape: raw.shape,
+            strides: raw.strides,
+        })
+    }
+}
+
 impl<T> Tensor<T> {
     pub fn new(shape: impl Into<Shape>) -> Self
     where
         T: Default + Clone,
     {
         let shape = shape.into();
+        Tensor {
+            data: vec![T::default(); shape.size()],
+            strides: contiguous_strides(&shape),
+            shape,
+        }
+    }
 
-        let mut strides = vec![1; shape.rank()];
-        for i in (0..shape.rank() - 1).rev() {
-            strides[i] = strides[i + 1] * shape.dim(i + 1);
+    /// Wraps `data`, laid out in row-major order, as a tensor of the given shape.
+    pub fn from_vec(data: Vec<T>, shape: impl Into<Shape>) -> Self {
+        let shape = shape.into();
+        if data.len() != shape.size() {
+            panic!(
+                "Tensor of shape {:?} cannot hold {} values",
+                shape.dims,
+                data.len()
+            );
         }
 
         Tensor {
-            data: vec![T::default(); shape.size()],
+            data,
+            strides: contiguous_strides(&shape),
             shape,
-            strides,
         }
     }
+
+    /// Reinterprets the row-major data with a new shape of the same size.
+    pub fn reshape(self, shape: impl Into<Shape>) -> Self {
+        Tensor::from_vec(self.data, shape)
+    }
+
+    pub fn shape(&self) -> &Shape {
+        &self.shape
+    }
+}
+
+fn contiguous_strides(shape: &Shape) -> Vec<usize> {
+    let mut strides = vec![1; shape.rank()];
+    for i in (0..shape.rank().saturating_sub(1)).rev() {
+        strides[i] = strides[i + 1] * shape.dim(i + 1);
+    }
+
+    strides
+}
+
+/// Flattens every dimension after the first, so a `(batch, channels, height, width)`
+/// tensor becomes a matrix with one sample per row.
+impl<T> From<Tensor<T>> for Matrix<T>
+where
+    T: Default + Clone,
+{
+    fn from(tensor: Tensor<T>) -> Self {
+        let rows = if tensor.shape.is_empty() {
+            1
+        } else {
+            tensor.shape.dim(0)
+        };
+        let cols = tensor.data.len().checked_div(rows).unwrap_or(0);
+
+        Matrix::from(tensor.data).reshape((rows, cols))
+    }
+}
+
+/// The shape of an elementwise result between `a` and `b`, see [`Shape::broadcast`].
+fn broadcast_shape(a: &Shape, b: &Shape) -> Shape {
+    a.broadcast(b).unwrap_or_else(|| {
+        panic!(
+            "Tensor shapes {:?} and {:?} cannot be broadcast together",
+            a.dims, b.dims
+        )
+    })
+}
+
+/// The strides that read `tensor` as if it had `shape`, with 0 along the dimensions it is
+/// stretched over.
+fn broadcast_strides<T>(tensor: &Tensor<T>, shape: &Shape) -> Vec<usize> {
+    let missing = shape.rank() - tensor.shape.rank();
+    (0..shape.rank())
+        .map(|i| match i.checked_sub(missing) {
+            Some(i) if tensor.shape.dim(i) != 1 => tensor.strides[i],
+            _ => 0,
+        })
+        .collect()
+}
+
+/// Calls `f` with the offsets into `a` and `b`, given their broadcast strides, of every
+/// position of `shape` in row-major order.
+fn for_each_offset(shape: &Shape, a: &[usize], b: &[usize], mut f: impl FnMut(usize, usize)) {
+    let mut index = vec![0; shape.rank()];
+    let (mut a_offset, mut b_offset) = (0, 0);
+    for _ in 0..shape.size() {
+        f(a_offset, b_offset);
+
+        for dim in (0..shape.rank()).rev() {
+            index[dim] += 1;
+            a_offset += a[dim];
+            b_offset += b[dim];
+            if index[dim] < shape.dim(dim) {
+                break;
+            }
+
+            a_offset -= a[dim] * index[dim];
+            b_offset -= b[dim] * index[dim];
+            index[dim] = 0;
+        }
+    }
+}
+
+/// Applies `op` to every pair of values of `a` and `b` after broadcasting them to a common
+/// shape.
+fn broadcast<T: Clone>(a: &Tensor<T>, b: &Tensor<T>, op: impl Fn(T, T) -> T) -> Tensor<T> {
+    let shape = broadcast_shape(&a.shape, &b.shape);
+    let mut data = Vec::with_capacity(shape.size());
+    for_each_offset(
+        &shape,
+        &broadcast_strides(a, &shape),
+        &broadcast_strides(b, &shape),
+        |i, j| data.push(op(a.data[i].clone(), b.data[j].clone())),
+    );
+
+    Tensor::from_vec(data, shape)
+}
+
+macro_rules! elementwise {
+    ($Op:ident, $op:ident, $OpAssign:ident, $op_assign:ident, $symbol:tt) => {
+        impl<T> $OpAssign<&Tensor<T>> for Tensor<T>
+        where
+            T: $Op<Output = T> + Clone,
+        {
+            fn $op_assign(&mut self, other: &Tensor<T>) {
+                let shape = broadcast_shape(&self.shape, &other.shape);
+                if shape != self.shape {
+                    panic!(
+                        "Tensor of shape {:?} cannot be broadcast into shape {:?}",
+                        other.shape.dims, self.shape.dims
+                    );
+                }
+
+                let strides = broadcast_strides(other, &shape);
+                let data = &mut self.data;
+                for_each_offset(&shape, &self.strides, &strides, |i, j| {
+                    data[i] = data[i].clone() $symbol other.data[j].clone();
+                });
+            }
+        }
+
+        impl<T> $OpAssign for Tensor<T>
+        where
+            T: $Op<Output = T> + Clone,
+        {
+            fn $op_assign(&mut self, other: Tensor<T>) {
+                $OpAssign::$op_assign(self, &other);
+            }
+        }
+
+        impl<T> $OpAssign<T> for Tensor<T>
+        where
+            T: $Op<Output = T> + Clone,
+        {
+            fn $op_assign(&mut self, scaler: T) {
+                for a in self.data.iter_mut() {
+                    *a = a.clone() $symbol scaler.clone();
+                }
+            }
+        }
+
+        impl<T> $Op for Tensor<T>
+        where
+            T: $Op<Output = T> + Clone,
+        {
+            type Output = Self;
+
+            fn $op(self, other: Self) -> Self::Output {
+                self $symbol &other
+            }
+        }
+
+        impl<T> $Op<&Tensor<T>> for Tensor<T>
+        where
+            T: $Op<Output = T> + Clone,
+        {
+            type Output = Self;
+
+            fn $op(mut self, other: &Tensor<T>) -> Self::Output {
+                if broadcast_shape(&self.shape, &other.shape) != self.shape {
+                    return broadcast(&self, other, |a, b| a $symbol b);
+                }
+
+                $OpAssign::$op_assign(&mut self, other);
+                self
+            }
+        }
+
+        impl<T> $Op<&Tensor<T>> for &Tensor<T>
+        where
+            T: $Op<Output = T> + Clone,
+        {
+            type Output = Tensor<T>;
+
+            fn $op(self, other: &Tensor<T>) -> Self::Output {
+                broadcast(self, other, |a, b| a $symbol b)
+            }
+        }
+
+        impl<T> $Op<T> for Tensor<T>
+        where
+            T: $Op<Output = T> + Clone,
+        {
+            type Output = Self;
+
+            fn $op(mut self, scaler: T) -> Self::Output {
+                $OpAssign::$op_assign(&mut self, scaler);
+                self
+            }
+        }
+
+        impl<T> $Op<T> for &Tensor<T>
+        where
+            T: $Op<Output = T> + Clone,
+        {
+            type Output = Tensor<T>;
+
+            fn $op(self, scaler: T) -> Self::Output {
+                self.clone() $symbol scaler
+            }
+        }
+    };
 }
 
+elementwise!(Add, add, AddAssign, add_assign, +);
+elementwise!(Sub, sub, SubAssign, sub_assign, -);
+elementwise!(Mul, mul, MulAssign, mul_assign, *);
+elementwise!(Div, div, DivAssign, div_assign, /);
+
 impl<T> Index<usize> for Tensor<T> {
     type Output = T;
 
@@ -151,4 +436,97 @@ mod test {
         tensor_two[(0, 1, 2)] = 3.0;
         assert_eq!(tensor_two[(0, 1, 2)], 3.0);
     }
+
+    #[test]
+    fn test_tensor_reshape_and_flatten() {
+        let tensor = Tensor::from_vec((0..24).map(|x| x as f32).collect(), (2, 3, 2, 2));
+        assert_eq!(tensor.strides, vec![12, 4, 2, 1]);
+        assert_eq!(tensor[(1, 2, 0, 1)], 21.0);
+
+        let matrix = Matrix::from(tensor.clone());
+        assert_eq!(matrix.shape(), (2, 12));
+        assert_eq!(matrix[(1, 9)], 21.0);
+
+        let reshaped = tensor.reshape((4, 6));
+        assert_eq!(reshaped[(3, 3)], 21.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot hold 5 values")]
+    fn test_tensor_from_vec_checks_size() {
+        Tensor::from_vec(vec![0.0; 5], (2, 3));
+    }
+
+    #[test]
+    fn test_tensor_broadcasting() {
+        let batch = Tensor::from_vec((0..12).collect(), (2, 2, 3));
+        let bias = Tensor::from_vec(vec![10, 20, 30], 3);
+        let column = Tensor::from_vec(vec![1, 2], (2, 1));
+
+        let sum = &batch + &bias;
+        assert_eq!(sum.shape.dims, vec![2, 2, 3]);
+        assert_eq!(
+            sum.data,
+            vec![10, 21, 32, 13, 24, 35, 16, 27, 38, 19, 30, 41]
+        );
+        assert_eq!(&bias + &batch, sum);
+
+        let scaled = batch.clone() * &column;
+        assert_eq!(scaled.data, vec![0, 1, 2, 6, 8, 10, 6, 7, 8, 18, 20, 22]);
+
+        let outer = &column * &bias;
+        assert_eq!(outer.shape.dims, vec![2, 3]);
+        assert_eq!(outer.data, vec![10, 20, 30, 20, 40, 60]);
+
+        let mut shifted = batch.clone();
+        shifted -= &bias;
+        shifted += bias;
+        assert_eq!(shifted, batch);
+        assert_eq!((batch * 2 / 2).data, (0..12).collect::<Vec<_>>());
+    }
+
+    #[test]
+    #[should_panic(expected = "Tensor shapes [2, 3] and [2] cannot be broadcast together")]
+    fn test_tensor_broadcast_shape_mismatch() {
+        let _ = Tensor::<f32>::new((2, 3)) + Tensor::new(2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Tensor of shape [2, 3] cannot be broadcast into shape [3]")]
+    fn test_tensor_assign_broadcast_into_smaller() {
+        let mut tensor = Tensor::<f32>::new(3);
+        tensor += Tensor::new((2, 3));
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_tensor_serde() {
+        let mut tensor = Tensor::<f32>::new((2, 3));
+        tensor[(1, 2)] = 2.0;
+
+        let json = serde_json::to_string(&tensor).unwrap();
+        let restored = serde_json::from_str::<Tensor<f32>>(&json).unwrap();
+
+        assert_eq!(restored.data, tensor.data);
+        assert_eq!(restored.shape, tensor.shape);
+        assert_eq!(restored.strides, tensor.strides);
+        assert!(
+            serde_json::from_str::<Tensor<f32>>(
+                r#"{"data":[1.0],"shape":{"dims":[2]},"strides":[1]}"#
+            )
+            .is_err()
+        );
+        assert!(
+            serde_json::from_str::<Tensor<f32>>(
+                r#"{"data":[1.0,2.0,3.0,4.0],"shape":{"dims":[2,2]},"strides":[2,100]}"#
+            )
+            .is_err()
+        );
+
+        let transposed = serde_json::from_str::<Tensor<f32>>(
+            r#"{"data":[1.0,2.0,3.0,4.0],"shape":{"dims":[2,2]},"strides":[1,2]}"#,
+        )
+        .unwrap();
+        assert_eq!(transposed[(0, 1)], 3.0);
+    }
 }
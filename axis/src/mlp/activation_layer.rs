@@ -0,0 +1,99 @@
+use super::{Layer, LayerRecord};
+use crate::{
+    Matrix,
+    math::{Activation, Optimizer},
+};
+use std::io;
+
+/// Applies an [`Activation`] on its own, so other layers such as normalization or dropout
+/// can sit between a linear [`Dense`](super::Dense) layer and its nonlinearity.
+#[derive(PartialEq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ActivationLayer {
+    activation: Activation,
+    #[cfg_attr(feature = "serde", serde(skip, default = "super::layer::empty_cache"))]
+    input: Matrix<f32>,
+}
+
+impl ActivationLayer {
+    pub fn new(activation: Activation) -> Self {
+        ActivationLayer {
+            activation,
+            input: Matrix::new(0, 0),
+        }
+    }
+
+    /// Rebuilds a layer from a record written by [`Layer::record`].
+    pub fn from_record(record: &LayerRecord) -> io::Result<Self> {
+        Ok(ActivationLayer::new(record.get_attribute("activation")?))
+    }
+}
+
+impl Layer for ActivationLayer {
+    fn forward(&self, input: &Matrix<f32>) -> Matrix<f32> {
+        self.activation.forward(input)
+    }
+
+    fn feed_forward(&mut self, input: &Matrix<f32>) -> Matrix<f32> {
+        self.input = input.clone();
+        self.activation.forward(input)
+    }
+
+    fn backpropagate(
+        &mut self,
+        error: &Matrix<f32>,
+        _: &Matrix<f32>,
+        prev_output: &Matrix<f32>,
+    ) -> Matrix<f32> {
+        self.activation.backward(&self.input, prev_output, error)
+    }
+
+    /// The delta is already taken with respect to this layer's input.
+    fn backpropagate_delta(
+        &mut self,
+        delta: &Matrix<f32>,
+        _: &Matrix<f32>,
+        _: &Matrix<f32>,
+    ) -> Matrix<f32> {
+        delta.clone()
+    }
+
+    fn update(&mut self, _: &Optimizer) {}
+
+    fn activation(&self) -> Option<Activation> {
+        Some(self.activation)
+    }
+
+    fn record(&self, _: bool) -> Option<LayerRecord> {
+        Some(LayerRecord::new("activation").attribute("activation", self.activation))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{domain::random_provider, mlp::Dense};
+
+    #[test]
+    fn test_activation_layer_matches_dense_activation() {
+        random_provider::seed(42);
+
+        let mut fused = Dense::new((3, 2)).activation(Activation::Tanh);
+        let mut linear = fused.clone().activation(Activation::Linear);
+        let mut activation = ActivationLayer::new(Activation::Tanh);
+
+        let input = Matrix::from(vec![vec![0.5, -1.0, 2.0], vec![1.5, 0.25, -0.75]]);
+        let error = Matrix::from(vec![vec![0.1, -0.4], vec![0.3, 0.2]]);
+
+        let expected = fused.feed_forward(&input);
+        let hidden = linear.feed_forward(&input);
+        let output = activation.feed_forward(&hidden);
+        assert_eq!(output, expected);
+        assert_eq!(activation.forward(&hidden), expected);
+
+        let expected_error = fused.backpropagate(&error, &input, &expected);
+        let hidden_error = activation.backpropagate(&error, &hidden, &output);
+        let input_error = linear.backpropagate(&hidden_error, &input, &hidden);
+        assert_eq!(input_error, expected_error);
+    }
+}
@@ -0,0 +1,703 @@
+use super::{Dense, Layer, LayerRecord};
+use crate::{
+    Matrix,
+    math::{Initializer, Optimizer, Tensor},
+};
+use std::io;
+
+/// A mask letting every position attend only to itself and earlier positions. Entries are
+/// `true` where attention is allowed.
+pub fn causal_mask(seq: usize) -> Matrix<bool> {
+    let mut mask = Matrix::zeros((seq, seq));
+    for i in 0..seq {
+        for j in 0..=i {
+            mask[(i, j)] = true;
+        }
+    }
+
+    mask
+}
+
+/// Computes `softmax(Q·Kᵀ / √d)·V` for every sample of a batch, where `query` is
+/// `(batch, seq_q, d)`, `key` is `(batch, seq_k, d)` and `value` is `(batch, seq_k, d_v)`.
+/// The optional `(seq_q, seq_k)` mask is `true` where a query may attend to a key; a query
+/// with no allowed keys attends to nothing and returns zeros.
+pub fn scaled_dot_product_attention(
+    query: &Tensor<f32>,
+    key: &Tensor<f32>,
+    value: &Tensor<f32>,
+    mask: Option<&Matrix<bool>>,
+) -> Tensor<f32> {
+    let [batch, seq_q, d] = dims(query);
+    let [key_batch, seq_k, key_d] = dims(key);
+    let [value_batch, value_seq, d_v] = dims(value);
+    if key_batch != batch || value_batch != batch || key_d != d || value_seq != seq_k {
+        panic!(
+            "Attention shapes do not match: query {:?}, key {:?}, value {:?}",
+            query.shape().dims,
+            key.shape().dims,
+            value.shape().dims
+        );
+    }
+    check_mask(mask, (seq_q, seq_k));
+
+    let sample = |tensor: &Tensor<f32>, b: usize, rows: usize, cols: usize| {
+        Matrix::from(tensor.data[b * rows * cols..(b + 1) * rows * cols].to_vec())
+            .reshape((rows, cols))
+    };
+
+    let mut data = Vec::with_capacity(batch * seq_q * d_v);
+    for b in 0..batch {
+        let (output, _) = attend(
+            &sample(query, b, seq_q, d),
+            &sample(key, b, seq_k, d),
+            &sample(value, b, seq_k, d_v),
+            mask,
+        );
+        data.extend_from_slice(output.as_ref());
+    }
+
+    Tensor::from_vec(data, (batch, seq_q, d_v))
+}
+
+/// Multi-head self-attention over `(batch, seq, dim)` sequences, taken as tensors by
+/// [`MultiHeadAttention::forward_tensor`]. As a [`Layer`], like the recurrent layers, each
+/// row of the input holds one sample's positions one after another, which is what
+/// `Matrix::from` gives for a `(batch, seq, dim)` [`Tensor`]. The queries, keys and values
+/// are projected by [`Dense`] layers, split into `heads` heads of `dim / heads` features
+/// that attend independently, then concatenated and projected back to `dim`.
+#[derive(PartialEq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MultiHeadAttention {
+    dim: usize,
+    heads: usize,
+    query: Dense,
+    key: Dense,
+    value: Dense,
+    output: Dense,
+    mask: Option<Matrix<bool>>,
+    /// The projections input, queries, keys, values, attended values and output of the
+    /// last training pass, each with one position per row.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    tape: Vec<Matrix<f32>>,
+    /// The attention weights of every sample and head of the last training pass.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    weights: Vec<Matrix<f32>>,
+}
+
+impl MultiHeadAttention {
+    pub fn new(dim: usize, heads: usize) -> Self {
+        if heads == 0 || !dim.is_multiple_of(heads) {
+            panic!("Attention dimension {dim} cannot be split into {heads} heads");
+        }
+
+        let projection = || {
+            Dense::new((dim, dim))
+                .weight_initializer(Initializer::XavierUniform)
+                .bias_initializer(Initializer::Zeros)
+        };
+
+        MultiHeadAttention {
+            dim,
+            heads,
+            query: projection(),
+            key: projection(),
+            value: projection(),
+            output: projection(),
+            mask: None,
+            tape: Vec::new(),
+            weights: Vec::new(),
+        }
+    }
+
+    /// Rebuilds a layer from a record written by [`Layer::record`].
+    pub fn from_record(record: &LayerRecord) -> io::Result<Self> {
+        let dim: usize = record.get_attribute("dim")?;
+        let heads: usize = record.get_attribute("heads")?;
+        if heads == 0 || !dim.is_multiple_of(heads) {
+            return Err(super::record::invalid(format!(
+                "Attention dimension {dim} cannot be split into {heads} heads"
+            )));
+        }
+
+        let mask_size = record.get_attribute("mask_size")?;
+        let mask = if mask_size > 0 {
+            let mask = record.get_matrix("mask", (mask_size, mask_size))?;
+            let mut allowed = Matrix::zeros(mask.shape());
+            for (allowed, &value) in allowed.iter_mut().zip(mask.iter()) {
+                *allowed = value != 0.0;
+            }
+            Some(allowed)
+        } else {
+            None
+        };
+
+        let projection = |name: &str| {
+            let layer = Dense::from_record(&record.get_nested(name, "dense"))?;
+            if layer.shape() != (dim, dim) {
+                return Err(super::record::invalid(format!(
+                    "Attention {name} projection must have shape {:?}, got {:?}",
+                    (dim, dim),
+                    layer.shape()
+                )));
+            }
+            Ok(layer)
+        };
+
+        Ok(MultiHeadAttention {
+            dim,
+            heads,
+            query: projection("query")?,
+            key: projection("key")?,
+            value: projection("value")?,
+            output: projection("output")?,
+            mask,
+            tape: Vec::new(),
+            weights: Vec::new(),
+        })
+    }
+
+    /// Restricts which positions attend to which, see [`causal_mask`]. The mask is
+    /// `(seq, seq)` with `true` where attention is allowed.
+    pub fn mask(mut self, mask: Matrix<bool>) -> Self {
+        if mask.rows() != mask.cols() {
+            panic!(
+                "Self-attention mask must be square, got shape {:?}",
+                mask.shape()
+            );
+        }
+
+        self.mask = Some(mask);
+        self
+    }
+
+    /// Like [`Layer::forward`], but on a `(batch, seq, dim)` tensor, with an optional
+    /// `(batch, seq)` key padding mask, `true` where a position holds a real token and
+    /// `false` where it is padding that no position may attend to.
+    pub fn forward_tensor(
+        &self,
+        input: &Tensor<f32>,
+        padding: Option<&Matrix<bool>>,
+    ) -> Tensor<f32> {
+        let [_, seq, _] = dims(input);
+        let shape = input.shape().dims.clone();
+        let output = self.forward_padded(&sequence_rows(input, seq, self.dim), padding);
+        Tensor::from_vec(output.as_ref().to_vec(), shape)
+    }
+
+    /// Like [`Layer::forward`], with an optional key padding mask as for
+    /// [`MultiHeadAttention::forward_tensor`].
+    pub fn forward_padded(
+        &self,
+        input: &Matrix<f32>,
+        padding: Option<&Matrix<bool>>,
+    ) -> Matrix<f32> {
+        let seq = self.positions(input, padding);
+        let x = input.clone().reshape((input.rows() * seq, self.dim));
+
+        let (attended, _) = self.attend_heads(
+            seq,
+            &self.query.forward(&x),
+            &self.key.forward(&x),
+            &self.value.forward(&x),
+            padding,
+        );
+        self.output.forward(&attended).reshape(input.shape())
+    }
+
+    /// Like [`Layer::feed_forward`], with an optional key padding mask as for
+    /// [`MultiHeadAttention::forward_tensor`]. [`Layer::backpropagate`] follows it as usual.
+    pub fn feed_forward_padded(
+        &mut self,
+        input: &Matrix<f32>,
+        padding: Option<&Matrix<bool>>,
+    ) -> Matrix<f32> {
+        let seq = self.positions(input, padding);
+        let x = input.clone().reshape((input.rows() * seq, self.dim));
+
+        let queries = self.query.feed_forward(&x);
+        let keys = self.key.feed_forward(&x);
+        let values = self.value.feed_forward(&x);
+        let (attended, weights) = self.attend_heads(seq, &queries, &keys, &values, padding);
+        let output = self.output.feed_forward(&attended);
+
+        self.weights = weights;
+        self.tape = vec![x, queries, keys, values, attended, output.clone()];
+        output.reshape(input.shape())
+    }
+
+    fn positions(&self, input: &Matrix<f32>, padding: Option<&Matrix<bool>>) -> usize {
+        if self.dim == 0 || !input.cols().is_multiple_of(self.dim) {
+            panic!(
+                "Attention layer expected positions with {} features, got {} values",
+                self.dim,
+                input.cols()
+            );
+        }
+
+        let seq = input.cols() / self.dim;
+        check_mask(self.mask.as_ref(), (seq, seq));
+        if let Some(padding) = padding
+            && padding.shape() != (input.rows(), seq)
+        {
+            panic!(
+                "Attention padding mask has shape {:?}, expected {:?}",
+                padding.shape(),
+                (input.rows(), seq)
+            );
+        }
+
+        seq
+    }
+
+    /// The mask of sample `b`: the attention mask with the padded keys removed.
+    fn sample_mask(
+        &self,
+        b: usize,
+        seq: usize,
+        padding: Option<&Matrix<bool>>,
+    ) -> Option<Matrix<bool>> {
+        let padding = padding?;
+        let mut mask = Matrix::zeros((seq, seq));
+        for i in 0..seq {
+            for j in 0..seq {
+                mask[(i, j)] = self.mask.as_ref().is_none_or(|m| m[(i, j)]) && padding[(b, j)];
+            }
+        }
+
+        Some(mask)
+    }
+
+    /// Attends within every sample and head of the projected `queries`, `keys` and
+    /// `values`, returning the concatenated heads and the attention weights.
+    fn attend_heads(
+        &self,
+        seq: usize,
+        queries: &Matrix<f32>,
+        keys: &Matrix<f32>,
+        values: &Matrix<f32>,
+        padding: Option<&Matrix<bool>>,
+    ) -> (Matrix<f32>, Vec<Matrix<f32>>) {
+        let head_dim = self.dim / self.heads;
+        let mut attended = Matrix::zeros(queries.shape());
+        let mut weights = Vec::new();
+
+        for b in 0..queries.rows() / seq.max(1) {
+            let sample_mask = self.sample_mask(b, seq, padding);
+            let mask = sample_mask.as_ref().or(self.mask.as_ref());
+            for h in 0..self.heads {
+                let block = |m: &Matrix<f32>| block(m, (b * seq, h * head_dim), (seq, head_dim));
+                let (output, probabilities) =
+                    attend(&block(queries), &block(keys), &block(values), mask);
+                set_block(&mut attended, (b * seq, h * head_dim), &output);
+                weights.push(probabilities);
+            }
+        }
+
+        (attended, weights)
+    }
+
+    fn sublayers(&self) -> [&Dense; 4] {
+        [&self.query, &self.key, &self.value, &self.output]
+    }
+}
+
+impl Layer for MultiHeadAttention {
+    fn forward(&self, input: &Matrix<f32>) -> Matrix<f32> {
+        self.forward_padded(input, None)
+    }
+
+    fn feed_forward(&mut self, input: &Matrix<f32>) -> Matrix<f32> {
+        self.feed_forward_padded(input, None)
+    }
+
+    fn backpropagate(
+        &mut self,
+        error: &Matrix<f32>,
+        prev_input: &Matrix<f32>,
+        _: &Matrix<f32>,
+    ) -> Matrix<f32> {
+        let [x, queries, keys, values, attended, output] = &self.tape[..] else {
+            panic!("Attention layer must run a training pass before backpropagating");
+        };
+        let seq = queries.rows() / prev_input.rows().max(1);
+        let head_dim = self.dim / self.heads;
+        let error = error.clone().reshape(output.shape());
+
+        let attended_error = self.output.backpropagate(&error, attended, output);
+        let mut query_error = Matrix::zeros(queries.shape());
+        let mut key_error = Matrix::zeros(keys.shape());
+        let mut value_error = Matrix::zeros(values.shape());
+
+        for b in 0..prev_input.rows() {
+            for h in 0..self.heads {
+                let origin = (b * seq, h * head_dim);
+                let block = |m: &Matrix<f32>| block(m, origin, (seq, head_dim));
+                let (dq, dk, dv) = attend_backward(
+                    &block(&attended_error),
+                    &block(queries),
+                    &block(keys),
+                    &block(values),
+                    &self.weights[b * self.heads + h],
+                );
+                set_block(&mut query_error, origin, &dq);
+                set_block(&mut key_error, origin, &dk);
+                set_block(&mut value_error, origin, &dv);
+            }
+        }
+
+        let input_error = self.query.backpropagate(&query_error, x, queries)
+            + self.key.backpropagate(&key_error, x, keys)
+            + self.value.backpropagate(&value_error, x, values);
+        input_error.reshape(prev_input.shape())
+    }
+
+    fn update(&mut self, optimizer: &Optimizer) {
+        self.query.update(optimizer);
+        self.key.update(optimizer);
+        self.value.update(optimizer);
+        self.output.update(optimizer);
+    }
+
+    fn parameters(&self) -> Vec<&Matrix<f32>> {
+        self.sublayers()
+            .into_iter()
+            .flat_map(|layer| layer.parameters())
+            .collect()
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Matrix<f32>> {
+        let mut parameters = self.query.parameters_mut();
+        parameters.extend(self.key.parameters_mut());
+        parameters.extend(self.value.parameters_mut());
+        parameters.extend(self.output.parameters_mut());
+        parameters
+    }
+
+    fn gradients(&self) -> Vec<&Matrix<f32>> {
+        self.sublayers()
+            .into_iter()
+            .flat_map(|layer| layer.gradients())
+            .collect()
+    }
+
+    fn gradients_mut(&mut self) -> Vec<&mut Matrix<f32>> {
+        let mut gradients = self.query.gradients_mut();
+        gradients.extend(self.key.gradients_mut());
+        gradients.extend(self.value.gradients_mut());
+        gradients.extend(self.output.gradients_mut());
+        gradients
+    }
+
+    fn record(&self, optimizer_state: bool) -> Option<LayerRecord> {
+        let mut record = LayerRecord::new("multi_head_attention")
+            .attribute("dim", self.dim)
+            .attribute("heads", self.heads)
+            .attribute(
+                "mask_size",
+                self.mask.as_ref().map_or(0, |mask| mask.rows()),
+            );
+
+        if let Some(mask) = &self.mask {
+            let mut values = Matrix::zeros(mask.shape());
+            for (value, &allowed) in values.iter_mut().zip(mask.iter()) {
+                *value = allowed as u8 as f32;
+            }
+            record = record.matrix("mask", values);
+        }
+
+        for (name, layer) in ["query", "key", "value", "output"]
+            .into_iter()
+            .zip(self.sublayers())
+        {
+            record = record.nested(name, layer.record(optimizer_state)?);
+        }
+
+        Some(record)
+    }
+}
+
+fn dims(tensor: &Tensor<f32>) -> [usize; 3] {
+    match tensor.shape().dims[..] {
+        [batch, seq, dim] => [batch, seq, dim],
+        _ => panic!(
+            "Attention expects (batch, seq, dim) tensors, got shape {:?}",
+            tensor.shape().dims
+        ),
+    }
+}
+
+/// The rows of a `(batch, seq, dim)` tensor whose positions must have `dim` features.
+pub(crate) fn sequence_rows(input: &Tensor<f32>, seq: usize, dim: usize) -> Matrix<f32> {
+    let [batch, _, input_dim] = dims(input);
+    if input_dim != dim {
+        panic!(
+            "Attention layer expected a (batch, seq, {dim}) tensor, got shape {:?}",
+            input.shape().dims
+        );
+    }
+
+    Matrix::from(input.data.clone()).reshape((batch, seq * dim))
+}
+
+fn check_mask(mask: Option<&Matrix<bool>>, shape: (usize, usize)) {
+    if let Some(mask) = mask
+        && mask.shape() != shape
+    {
+        panic!(
+            "Attention mask has shape {:?}, expected {:?}",
+            mask.shape(),
+            shape
+        );
+    }
+}
+
+/// Attention for a single sample and head. Returns the output and the attention weights.
+fn attend(
+    query: &Matrix<f32>,
+    key: &Matrix<f32>,
+    value: &Matrix<f32>,
+    mask: Option<&Matrix<bool>>,
+) -> (Matrix<f32>, Matrix<f32>) {
+    let scale = 1.0 / (query.cols().max(1) as f32).sqrt();
+    let mut weights = query.dot(&key.transpose()) * scale;
+
+    for i in 0..weights.rows() {
+        let allowed = |j: usize| mask.is_none_or(|mask| mask[(i, j)]);
+        let max = (0..weights.cols())
+            .filter(|&j| allowed(j))
+            .map(|j| weights[(i, j)])
+            .fold(f32::NEG_INFINITY, f32::max);
+
+        let mut sum = 0.0;
+        for j in 0..weights.cols() {
+            weights[(i, j)] = if allowed(j) {
+                (weights[(i, j)] - max).exp()
+            } else {
+                0.0
+            };
+            sum += weights[(i, j)];
+        }
+
+        for j in 0..weights.cols() {
+            if sum > 0.0 {
+                weights[(i, j)] /= sum;
+            }
+        }
+    }
+
+    (weights.dot(value), weights)
+}
+
+/// The errors of the query, key and value of [`attend`] given the error of its output.
+/// Masked positions have zero weight, so no error flows through them.
+fn attend_backward(
+    error: &Matrix<f32>,
+    query: &Matrix<f32>,
+    key: &Matrix<f32>,
+    value: &Matrix<f32>,
+    weights: &Matrix<f32>,
+) -> (Matrix<f32>, Matrix<f32>, Matrix<f32>) {
+    let scale = 1.0 / (query.cols().max(1) as f32).sqrt();
+    let value_error = weights.transpose().dot(error);
+    let weight_error = error.dot(&value.transpose());
+
+    let mut score_error = Matrix::zeros(weights.shape());
+    for i in 0..weights.rows() {
+        let dot = (0..weights.cols())
+            .map(|j| weights[(i, j)] * weight_error[(i, j)])
+            .sum::<f32>();
+        for j in 0..weights.cols() {
+            score_error[(i, j)] = weights[(i, j)] * (weight_error[(i, j)] - dot) * scale;
+        }
+    }
+
+    (
+        score_error.dot(key),
+        score_error.transpose().dot(query),
+        value_error,
+    )
+}
+
+fn block(matrix: &Matrix<f32>, origin: (usize, usize), shape: (usize, usize)) -> Matrix<f32> {
+    let mut output = Matrix::zeros(shape);
+    for i in 0..shape.0 {
+        for j in 0..shape.1 {
+            output[(i, j)] = matrix[(origin.0 + i, origin.1 + j)];
+        }
+    }
+
+    output
+}
+
+fn set_block(matrix: &mut Matrix<f32>, origin: (usize, usize), value: &Matrix<f32>) {
+    for i in 0..value.rows() {
+        for j in 0..value.cols() {
+            matrix[(origin.0 + i, origin.1 + j)] = value[(i, j)];
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        domain::random_provider,
+        math::{Activation, Loss},
+        mlp::{MultiLayerPerceptron, gradient_check},
+    };
+
+    #[test]
+    fn test_uniform_attention_averages_values() {
+        let query = Tensor::from_vec(vec![0.0; 4], (1, 2, 2));
+        let value = Tensor::from_vec(vec![1.0, 2.0, 3.0, 6.0], (1, 2, 2));
+
+        let output = scaled_dot_product_attention(&query, &query, &value, None);
+        assert_eq!(output.data, vec![2.0, 4.0, 2.0, 4.0]);
+
+        let output = scaled_dot_product_attention(&query, &query, &value, Some(&causal_mask(2)));
+        assert_eq!(output.data, vec![1.0, 2.0, 2.0, 4.0]);
+    }
+
+    #[test]
+    fn test_attention_prefers_matching_keys() {
+        let query = Tensor::from_vec(vec![10.0, 0.0], (1, 1, 2));
+        let key = Tensor::from_vec(vec![0.0, 10.0, 10.0, 0.0], (1, 2, 2));
+        let value = Tensor::from_vec(vec![-1.0, 1.0], (1, 2, 1));
+
+        let output = scaled_dot_product_attention(&query, &key, &value, None);
+        assert_eq!(output.shape().dims, vec![1, 1, 1]);
+        assert!((output.data[0] - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    #[should_panic(expected = "Attention mask has shape (2, 2), expected (3, 3)")]
+    fn test_mask_must_match_sequence() {
+        random_provider::seed(1);
+        MultiHeadAttention::new(4, 2)
+            .mask(causal_mask(2))
+            .forward(&Matrix::zeros((1, 12)));
+    }
+
+    #[test]
+    fn test_causal_attention_ignores_later_positions() {
+        random_provider::seed(2);
+
+        let attention = MultiHeadAttention::new(4, 2).mask(causal_mask(3));
+        let input = Tensor::from_vec(
+            Matrix::random((1, 12), -1.0..1.0).as_ref().to_vec(),
+            (1, 3, 4),
+        );
+        let mut changed = input.clone();
+        changed.data[8..].iter_mut().for_each(|x| *x += 1.0);
+
+        let output = attention.forward_tensor(&input, None);
+        let changed = attention.forward_tensor(&changed, None);
+        assert_eq!(output.shape().dims, vec![1, 3, 4]);
+        assert_eq!(output.data[..8], changed.data[..8]);
+        assert_ne!(output.data[8..], changed.data[8..]);
+    }
+
+    #[test]
+    fn test_padding_mask_ignores_padded_positions() {
+        random_provider::seed(3);
+
+        let mut attention = MultiHeadAttention::new(4, 2);
+        let input = Tensor::from_vec(
+            Matrix::random((2, 12), -1.0..1.0).as_ref().to_vec(),
+            (2, 3, 4),
+        );
+        // The first sample has one padded position at the end, the second none.
+        let padding = Matrix::from(vec![true, true, false, true, true, true]).reshape((2, 3));
+        let mut changed = input.clone();
+        changed.data[8..12].iter_mut().for_each(|x| *x += 1.0);
+
+        let output = attention.forward_tensor(&input, Some(&padding));
+        let repadded = attention.forward_tensor(&changed, Some(&padding));
+        assert_eq!(output.data[..8], repadded.data[..8]);
+        assert_eq!(output.data[12..], repadded.data[12..]);
+        assert_ne!(
+            output.data[..8],
+            attention.forward_tensor(&changed, None).data[..8]
+        );
+
+        let rows = Matrix::from(input.clone());
+        assert_eq!(
+            attention.forward_padded(&rows, Some(&padding)).as_ref(),
+            &output.data[..]
+        );
+        assert_eq!(
+            attention
+                .feed_forward_padded(&rows, Some(&padding))
+                .as_ref(),
+            &output.data[..]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Attention padding mask has shape (1, 3), expected (2, 3)")]
+    fn test_padding_mask_must_match_batch() {
+        random_provider::seed(1);
+        let mut attention = MultiHeadAttention::new(4, 2);
+        attention.feed_forward_padded(&Matrix::zeros((2, 12)), Some(&Matrix::zeros((1, 3))));
+    }
+
+    #[test]
+    fn test_multi_head_attention_gradients() {
+        for mask in [None, Some(causal_mask(3))] {
+            random_provider::scoped_seed(4, || {
+                let mut attention = MultiHeadAttention::new(4, 2);
+                if let Some(mask) = mask {
+                    attention = attention.mask(mask);
+                }
+                let mut model = MultiLayerPerceptron::new()
+                    .layer(Dense::new((2, 12)).weight_initializer(Initializer::XavierUniform))
+                    .layer(attention)
+                    .layer(Dense::new((12, 2)).activation(Activation::Tanh));
+
+                let input = Matrix::random((3, 2), -1.0..1.0);
+                let target = Matrix::random((3, 2), -1.0..1.0);
+                let errors = gradient_check(&mut model, &input, &target, &Loss::MSE, 1e-2);
+                for (layer, error) in errors.iter().enumerate() {
+                    assert!(*error < 1e-2, "layer {layer}: relative error {error}");
+                }
+            });
+        }
+    }
+
+    #[test]
+    fn test_record_round_trip() {
+        random_provider::seed(6);
+
+        let attention = MultiHeadAttention::new(6, 3).mask(causal_mask(2));
+        let loaded = MultiHeadAttention::from_record(&attention.record(false).unwrap()).unwrap();
+        assert_eq!(loaded, attention);
+    }
+
+    #[test]
+    fn test_from_record_rejects_bad_shapes() {
+        random_provider::seed(7);
+
+        let record = MultiHeadAttention::new(4, 2).record(false).unwrap();
+        for (name, value, message) in [
+            ("heads", "3", "cannot be split into 3 heads"),
+            ("heads", "0", "cannot be split into 0 heads"),
+            (
+                "dim",
+                "6",
+                "query projection must have shape (6, 6), got (4, 4)",
+            ),
+        ] {
+            let mut record = record.clone();
+            for (key, old) in record.attributes.iter_mut() {
+                if key == name {
+                    *old = value.to_string();
+                }
+            }
+
+            let error = MultiHeadAttention::from_record(&record).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+            assert!(error.to_string().contains(message), "{error}");
+        }
+    }
+}
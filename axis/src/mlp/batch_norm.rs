@@ -0,0 +1,335 @@
+use super::{Layer, LayerRecord};
+use crate::{
+    Matrix,
+    math::{Optimizer, OptimizerState},
+};
+use std::io;
+
+/// Normalizes each feature over the batch to zero mean and unit variance, then scales and
+/// shifts it by the learnable `gamma` and `beta`. Training uses the statistics of the
+/// current batch and folds them into running estimates, which are used at inference.
+#[derive(PartialEq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BatchNorm1d {
+    features: usize,
+    momentum: f32,
+    epsilon: f32,
+    gamma: Matrix<f32>,
+    beta: Matrix<f32>,
+    running_mean: Matrix<f32>,
+    running_var: Matrix<f32>,
+    gamma_gradient: Matrix<f32>,
+    beta_gradient: Matrix<f32>,
+    gamma_state: OptimizerState,
+    beta_state: OptimizerState,
+    #[cfg_attr(feature = "serde", serde(skip, default = "super::layer::empty_cache"))]
+    normalized: Matrix<f32>,
+    #[cfg_attr(feature = "serde", serde(skip, default = "super::layer::empty_cache"))]
+    inv_std: Matrix<f32>,
+}
+
+impl BatchNorm1d {
+    pub fn new(features: usize) -> Self {
+        BatchNorm1d {
+            features,
+            momentum: 0.1,
+            epsilon: 1e-5,
+            gamma: Matrix::ones((1, features)),
+            beta: Matrix::zeros((1, features)),
+            running_mean: Matrix::zeros((1, features)),
+            running_var: Matrix::ones((1, features)),
+            gamma_gradient: Matrix::zeros((1, features)),
+            beta_gradient: Matrix::zeros((1, features)),
+            gamma_state: OptimizerState::new(),
+            beta_state: OptimizerState::new(),
+            normalized: Matrix::new(0, features),
+            inv_std: Matrix::new(0, features),
+        }
+    }
+
+    /// Rebuilds a layer from a record written by [`Layer::record`].
+    pub fn from_record(record: &LayerRecord) -> io::Result<Self> {
+        let features = record.get_attribute("features")?;
+        let shape = (1, features);
+
+        Ok(BatchNorm1d {
+            momentum: record.get_attribute("momentum")?,
+            epsilon: record.get_attribute("epsilon")?,
+            gamma: record.get_matrix("gamma", shape)?,
+            beta: record.get_matrix("beta", shape)?,
+            running_mean: record.get_matrix("running_mean", shape)?,
+            running_var: record.get_matrix("running_var", shape)?,
+            gamma_state: record.get_optimizer_state("gamma_state")?,
+            beta_state: record.get_optimizer_state("beta_state")?,
+            ..BatchNorm1d::new(features)
+        })
+    }
+
+    /// Sets how quickly the running statistics follow the batch statistics. The default is
+    /// `0.1`.
+    pub fn momentum(mut self, momentum: f32) -> Self {
+        self.momentum = momentum;
+        self
+    }
+
+    /// Sets the value added to the variance for numerical stability. The default is `1e-5`.
+    pub fn epsilon(mut self, epsilon: f32) -> Self {
+        self.epsilon = epsilon;
+        self
+    }
+
+    pub fn running_mean(&self) -> &Matrix<f32> {
+        &self.running_mean
+    }
+
+    pub fn running_var(&self) -> &Matrix<f32> {
+        &self.running_var
+    }
+
+    fn check_input(&self, input: &Matrix<f32>) {
+        if input.cols() != self.features {
+            panic!(
+                "BatchNorm1d layer expected {} input features, got {}",
+                self.features,
+                input.cols()
+            );
+        }
+    }
+}
+
+impl Layer for BatchNorm1d {
+    fn forward(&self, input: &Matrix<f32>) -> Matrix<f32> {
+        self.check_input(input);
+
+        let mut output = input.clone();
+        for i in 0..output.rows() {
+            for j in 0..output.cols() {
+                let normalized = (output[(i, j)] - self.running_mean[(0, j)])
+                    / (self.running_var[(0, j)] + self.epsilon).sqrt();
+                output[(i, j)] = self.gamma[(0, j)] * normalized + self.beta[(0, j)];
+            }
+        }
+
+        output
+    }
+
+    fn feed_forward(&mut self, input: &Matrix<f32>) -> Matrix<f32> {
+        self.check_input(input);
+
+        let rows = input.rows();
+        let n = rows.max(1) as f32;
+        let mut normalized = input.clone();
+        let mut output = input.clone();
+        let mut inv_stds = Matrix::zeros((1, self.features));
+        for j in 0..self.features {
+            let mean = (0..rows).map(|i| input[(i, j)]).sum::<f32>() / n;
+            let var = (0..rows)
+                .map(|i| (input[(i, j)] - mean).powi(2))
+                .sum::<f32>()
+                / n;
+            let inv_std = 1.0 / (var + self.epsilon).sqrt();
+
+            for i in 0..rows {
+                normalized[(i, j)] = (input[(i, j)] - mean) * inv_std;
+                output[(i, j)] = self.gamma[(0, j)] * normalized[(i, j)] + self.beta[(0, j)];
+            }
+
+            // The running variance uses the unbiased estimate, as it stands in for the
+            // population variance at inference.
+            let unbiased = if rows > 1 { var * n / (n - 1.0) } else { var };
+            self.running_mean[(0, j)] =
+                (1.0 - self.momentum) * self.running_mean[(0, j)] + self.momentum * mean;
+            self.running_var[(0, j)] =
+                (1.0 - self.momentum) * self.running_var[(0, j)] + self.momentum * unbiased;
+            inv_stds[(0, j)] = inv_std;
+        }
+
+        self.normalized = normalized;
+        self.inv_std = inv_stds;
+        output
+    }
+
+    fn backpropagate(
+        &mut self,
+        error: &Matrix<f32>,
+        _: &Matrix<f32>,
+        _: &Matrix<f32>,
+    ) -> Matrix<f32> {
+        let rows = error.rows();
+        let n = rows.max(1) as f32;
+        let mut input_error = Matrix::zeros(error.shape());
+        for j in 0..self.features {
+            let mut error_sum = 0.0;
+            let mut error_dot = 0.0;
+            for i in 0..rows {
+                error_sum += error[(i, j)];
+                error_dot += error[(i, j)] * self.normalized[(i, j)];
+            }
+
+            self.gamma_gradient[(0, j)] += error_dot;
+            self.beta_gradient[(0, j)] += error_sum;
+
+            let scale = self.gamma[(0, j)] * self.inv_std[(0, j)] / n;
+            for i in 0..rows {
+                input_error[(i, j)] =
+                    scale * (n * error[(i, j)] - error_sum - self.normalized[(i, j)] * error_dot);
+            }
+        }
+
+        input_error
+    }
+
+    fn update(&mut self, optimizer: &Optimizer) {
+        optimizer.update(
+            &mut self.gamma_state,
+            &mut self.gamma,
+            &mut self.gamma_gradient,
+        );
+        optimizer.update(
+            &mut self.beta_state,
+            &mut self.beta,
+            &mut self.beta_gradient,
+        );
+    }
+
+    fn parameters(&self) -> Vec<&Matrix<f32>> {
+        vec![&self.gamma, &self.beta]
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Matrix<f32>> {
+        vec![&mut self.gamma, &mut self.beta]
+    }
+
+    fn buffers(&self) -> Vec<&Matrix<f32>> {
+        vec![&self.running_mean, &self.running_var]
+    }
+
+    fn buffers_mut(&mut self) -> Vec<&mut Matrix<f32>> {
+        vec![&mut self.running_mean, &mut self.running_var]
+    }
+
+    fn gradients(&self) -> Vec<&Matrix<f32>> {
+        vec![&self.gamma_gradient, &self.beta_gradient]
+    }
+
+    fn gradients_mut(&mut self) -> Vec<&mut Matrix<f32>> {
+        vec![&mut self.gamma_gradient, &mut self.beta_gradient]
+    }
+
+    fn record(&self, optimizer_state: bool) -> Option<LayerRecord> {
+        let record = LayerRecord::new("batch_norm")
+            .attribute("features", self.features)
+            .attribute("momentum", self.momentum)
+            .attribute("epsilon", self.epsilon)
+            .matrix("gamma", self.gamma.clone())
+            .matrix("beta", self.beta.clone())
+            .matrix("running_mean", self.running_mean.clone())
+            .matrix("running_var", self.running_var.clone());
+
+        if optimizer_state {
+            Some(
+                record
+                    .optimizer_state("gamma_state", &self.gamma_state)
+                    .optimizer_state("beta_state", &self.beta_state),
+            )
+        } else {
+            Some(record)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn batch() -> Matrix<f32> {
+        Matrix::from(vec![
+            vec![1.0, -2.0, 0.5],
+            vec![3.0, 0.0, 0.25],
+            vec![-1.0, 4.0, 1.0],
+            vec![0.5, 1.0, -0.75],
+        ])
+    }
+
+    #[test]
+    fn test_batch_norm_normalizes_batch() {
+        let mut norm = BatchNorm1d::new(3);
+        let output = norm.feed_forward(&batch());
+
+        for j in 0..3 {
+            let mean = (0..4).map(|i| output[(i, j)]).sum::<f32>() / 4.0;
+            let var = (0..4).map(|i| (output[(i, j)] - mean).powi(2)).sum::<f32>() / 4.0;
+            assert!(mean.abs() < 1e-5);
+            assert!((var - 1.0).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_batch_norm_eval_uses_running_statistics() {
+        let mut norm = BatchNorm1d::new(3).momentum(1.0);
+        let input = batch();
+        norm.feed_forward(&input);
+
+        // Column 0 has mean 0.875 and squared deviations summing to 8.1875.
+        let var = 8.1875 / 3.0;
+        assert!((norm.running_mean()[(0, 0)] - 0.875).abs() < 1e-6);
+        assert!((norm.running_var()[(0, 0)] - var).abs() < 1e-5);
+
+        let output = norm.forward(&input);
+        let expected = (input[(0, 0)] - 0.875) / (var + 1e-5).sqrt();
+        assert!((output[(0, 0)] - expected).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_batch_norm_gradient() {
+        let input = batch();
+        let weights = Matrix::from(vec![
+            vec![0.3, -1.2, 0.7],
+            vec![1.1, 0.4, -0.5],
+            vec![-0.8, 0.9, 0.2],
+            vec![0.6, -0.3, 1.5],
+        ]);
+        let mut norm = BatchNorm1d::new(3);
+        norm.gamma = Matrix::from(vec![vec![1.5, 0.5, -1.0]]);
+        norm.beta = Matrix::from(vec![vec![0.1, -0.2, 0.3]]);
+
+        let objective = |norm: &mut BatchNorm1d, input: &Matrix<f32>| {
+            let output = norm.feed_forward(input);
+            output
+                .iter()
+                .zip(weights.iter())
+                .map(|(o, w)| o * w)
+                .sum::<f32>()
+        };
+
+        norm.feed_forward(&input);
+        let input_error = norm.backpropagate(&weights, &input, &input);
+
+        let h = 1e-2;
+        for i in 0..input.rows() {
+            for j in 0..input.cols() {
+                let mut plus = input.clone();
+                plus[(i, j)] += h;
+                let mut minus = input.clone();
+                minus[(i, j)] -= h;
+
+                let numeric = (objective(&mut norm.clone(), &plus)
+                    - objective(&mut norm.clone(), &minus))
+                    / (2.0 * h);
+                assert!(
+                    (numeric - input_error[(i, j)]).abs() < 1e-2,
+                    "({i}, {j}): numeric {numeric}, analytic {}",
+                    input_error[(i, j)]
+                );
+            }
+        }
+
+        let normalized = norm.normalized.clone();
+        for j in 0..3 {
+            let expected = (0..4)
+                .map(|i| weights[(i, j)] * normalized[(i, j)])
+                .sum::<f32>();
+            assert!((norm.gamma_gradient[(0, j)] - expected).abs() < 1e-5);
+        }
+    }
+}
@@ -0,0 +1,526 @@
+use super::{Layer, LayerRecord};
+use crate::{
+    Matrix,
+    math::{Initializer, Optimizer, OptimizerState, Tensor},
+};
+use std::io;
+
+/// A 2D convolution over `(batch, channels, height, width)` inputs. [`Conv2d::forward_tensor`]
+/// takes and returns such tensors. As a [`Layer`] it receives a batch as a matrix with one
+/// sample per row; each row holds the sample's `(channels, height, width)` values in
+/// row-major order, which is exactly the data of the tensor converted with `Matrix::from`.
+/// The output rows are laid out the same way as `(filters, out_height, out_width)`, and a
+/// [`Flatten`](super::Flatten) layer hands them on to [`Dense`](super::Dense).
+#[derive(PartialEq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Conv2d {
+    input: (usize, usize, usize),
+    filters: usize,
+    kernel: (usize, usize),
+    stride: (usize, usize),
+    padding: (usize, usize),
+    dilation: (usize, usize),
+    weights: Matrix<f32>,
+    biases: Matrix<f32>,
+    weight_gradient: Matrix<f32>,
+    bias_gradient: Matrix<f32>,
+    weight_state: OptimizerState,
+    bias_state: OptimizerState,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    columns: Vec<Matrix<f32>>,
+}
+
+impl Conv2d {
+    /// Creates a convolution over inputs of shape `(channels, height, width)` producing
+    /// `filters` output channels with the given `(height, width)` kernel. Weights start
+    /// He-uniform and biases at zero; stride and dilation default to one, padding to zero.
+    pub fn new(input: (usize, usize, usize), filters: usize, kernel: (usize, usize)) -> Self {
+        let weight_shape = (filters, input.0 * kernel.0 * kernel.1);
+
+        let conv = Conv2d {
+            input,
+            filters,
+            kernel,
+            stride: (1, 1),
+            padding: (0, 0),
+            dilation: (1, 1),
+            weights: Matrix::zeros(weight_shape),
+            biases: Matrix::zeros((1, filters)),
+            weight_gradient: Matrix::zeros(weight_shape),
+            bias_gradient: Matrix::zeros((1, filters)),
+            weight_state: OptimizerState::new(),
+            bias_state: OptimizerState::new(),
+            columns: Vec::new(),
+        };
+
+        conv.weight_initializer(Initializer::HeUniform)
+    }
+
+    /// Rebuilds a layer from a record written by [`Layer::record`].
+    pub fn from_record(record: &LayerRecord) -> io::Result<Self> {
+        let input = (
+            record.get_attribute("channels")?,
+            record.get_attribute("height")?,
+            record.get_attribute("width")?,
+        );
+        let filters = record.get_attribute("filters")?;
+        let kernel = get_pair(record, "kernel")?;
+        let weight_shape = (filters, input.0 * kernel.0 * kernel.1);
+
+        let conv = Conv2d {
+            input,
+            filters,
+            kernel,
+            stride: get_nonzero_pair(record, "stride")?,
+            padding: get_pair(record, "padding")?,
+            dilation: get_nonzero_pair(record, "dilation")?,
+            weights: record.get_matrix("weights", weight_shape)?,
+            biases: record.get_matrix("biases", (1, filters))?,
+            weight_gradient: Matrix::zeros(weight_shape),
+            bias_gradient: Matrix::zeros((1, filters)),
+            weight_state: record.get_optimizer_state("weight_state")?,
+            bias_state: record.get_optimizer_state("bias_state")?,
+            columns: Vec::new(),
+        };
+
+        conv.try_output_shape()
+            .map(|_| conv)
+            .ok_or_else(|| super::record::invalid("Conv2d kernel does not fit its input".into()))
+    }
+
+    pub fn stride(mut self, stride: (usize, usize)) -> Self {
+        if stride.0 == 0 || stride.1 == 0 {
+            panic!("Stride must be greater than zero");
+        }
+
+        self.stride = stride;
+        self
+    }
+
+    /// Pads the input with `padding` zeros on each side of the height and width.
+    pub fn padding(mut self, padding: (usize, usize)) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Spaces the kernel taps `dilation` pixels apart.
+    pub fn dilation(mut self, dilation: (usize, usize)) -> Self {
+        if dilation.0 == 0 || dilation.1 == 0 {
+            panic!("Dilation must be greater than zero");
+        }
+
+        self.dilation = dilation;
+        self
+    }
+
+    pub fn weight_initializer(mut self, initializer: Initializer) -> Self {
+        let fan_in = self.input.0 * self.kernel.0 * self.kernel.1;
+        let fan_out = self.filters * self.kernel.0 * self.kernel.1;
+        self.weights = initializer.initialize(self.weights.shape(), fan_in, fan_out);
+        self
+    }
+
+    pub fn bias_initializer(mut self, initializer: Initializer) -> Self {
+        let fan_in = self.input.0 * self.kernel.0 * self.kernel.1;
+        let fan_out = self.filters * self.kernel.0 * self.kernel.1;
+        self.biases = initializer.initialize(self.biases.shape(), fan_in, fan_out);
+        self
+    }
+
+    /// Like [`Layer::forward`], but on a `(batch, channels, height, width)` tensor, returning
+    /// a `(batch, filters, out_height, out_width)` one.
+    pub fn forward_tensor(&self, input: &Tensor<f32>) -> Tensor<f32> {
+        let output = self.forward(&image_rows(input, self.input, "Conv2d"));
+        image_tensor(output, self.output_shape())
+    }
+
+    /// The `(filters, height, width)` shape of each output sample.
+    pub fn output_shape(&self) -> (usize, usize, usize) {
+        self.try_output_shape().unwrap_or_else(|| {
+            panic!(
+                "Conv2d kernel {:?} with dilation {:?} does not fit input {:?} with padding {:?}",
+                self.kernel, self.dilation, self.input, self.padding
+            )
+        })
+    }
+
+    fn try_output_shape(&self) -> Option<(usize, usize, usize)> {
+        Some((
+            self.filters,
+            output_size(
+                self.input.1,
+                self.kernel.0,
+                self.stride.0,
+                self.padding.0,
+                self.dilation.0,
+            )?,
+            output_size(
+                self.input.2,
+                self.kernel.1,
+                self.stride.1,
+                self.padding.1,
+                self.dilation.1,
+            )?,
+        ))
+    }
+
+    /// Unrolls every receptive field of one sample into a column, giving a
+    /// `(channels * kernel_height * kernel_width, out_height * out_width)` matrix.
+    fn im2col(&self, sample: &[f32], out: (usize, usize)) -> Matrix<f32> {
+        let (channels, height, width) = self.input;
+        let (kh, kw) = self.kernel;
+        let mut columns = Matrix::zeros((channels * kh * kw, out.0 * out.1));
+
+        for c in 0..channels {
+            for ki in 0..kh {
+                for kj in 0..kw {
+                    let row = (c * kh + ki) * kw + kj;
+                    for oy in 0..out.0 {
+                        let Some(y) = self.source(oy, ki, 0, height) else {
+                            continue;
+                        };
+                        for ox in 0..out.1 {
+                            if let Some(x) = self.source(ox, kj, 1, width) {
+                                columns[(row, oy * out.1 + ox)] =
+                                    sample[(c * height + y) * width + x];
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        columns
+    }
+
+    /// The inverse of [`Conv2d::im2col`], summing each column back onto the pixels it was
+    /// read from.
+    fn col2im(&self, columns: &Matrix<f32>, out: (usize, usize), sample: &mut [f32]) {
+        let (channels, height, width) = self.input;
+        let (kh, kw) = self.kernel;
+
+        for c in 0..channels {
+            for ki in 0..kh {
+                for kj in 0..kw {
+                    let row = (c * kh + ki) * kw + kj;
+                    for oy in 0..out.0 {
+                        let Some(y) = self.source(oy, ki, 0, height) else {
+                            continue;
+                        };
+                        for ox in 0..out.1 {
+                            if let Some(x) = self.source(ox, kj, 1, width) {
+                                sample[(c * height + y) * width + x] +=
+                                    columns[(row, oy * out.1 + ox)];
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// The input coordinate read by kernel tap `k` at output position `o` along `axis`, or
+    /// `None` when it falls in the padding.
+    fn source(&self, o: usize, k: usize, axis: usize, size: usize) -> Option<usize> {
+        let (stride, padding, dilation) = if axis == 0 {
+            (self.stride.0, self.padding.0, self.dilation.0)
+        } else {
+            (self.stride.1, self.padding.1, self.dilation.1)
+        };
+
+        (o * stride + k * dilation)
+            .checked_sub(padding)
+            .filter(|&i| i < size)
+    }
+
+    fn convolve(&self, input: &Matrix<f32>) -> (Matrix<f32>, Vec<Matrix<f32>>) {
+        let (channels, height, width) = self.input;
+        if input.cols() != channels * height * width {
+            panic!(
+                "Conv2d layer expected {} input values per sample, got {}",
+                channels * height * width,
+                input.cols()
+            );
+        }
+
+        let (_, out_height, out_width) = self.output_shape();
+        let out = (out_height, out_width);
+        let mut output = Matrix::zeros((input.rows(), self.filters * out.0 * out.1));
+        let mut columns = Vec::with_capacity(input.rows());
+
+        for (b, sample) in input.as_ref().chunks(input.cols()).enumerate() {
+            let sample_columns = self.im2col(sample, out);
+            let response = self.weights.dot(&sample_columns);
+            for f in 0..self.filters {
+                for p in 0..out.0 * out.1 {
+                    output[(b, f * out.0 * out.1 + p)] = response[(f, p)] + self.biases[(0, f)];
+                }
+            }
+
+            columns.push(sample_columns);
+        }
+
+        (output, columns)
+    }
+}
+
+impl Layer for Conv2d {
+    fn forward(&self, input: &Matrix<f32>) -> Matrix<f32> {
+        self.convolve(input).0
+    }
+
+    fn feed_forward(&mut self, input: &Matrix<f32>) -> Matrix<f32> {
+        let (output, columns) = self.convolve(input);
+        self.columns = columns;
+        output
+    }
+
+    fn backpropagate(
+        &mut self,
+        error: &Matrix<f32>,
+        _: &Matrix<f32>,
+        _: &Matrix<f32>,
+    ) -> Matrix<f32> {
+        let (channels, height, width) = self.input;
+        let (_, out_height, out_width) = self.output_shape();
+        let out = (out_height, out_width);
+        let mut input_error = Matrix::zeros((error.rows(), channels * height * width));
+
+        for (b, columns) in self.columns.iter().enumerate() {
+            let delta =
+                Matrix::from(error.as_ref()[b * error.cols()..(b + 1) * error.cols()].to_vec())
+                    .reshape((self.filters, out.0 * out.1));
+
+            self.weight_gradient += delta.dot(&columns.transpose());
+
+            for f in 0..self.filters {
+                self.bias_gradient[(0, f)] += (0..delta.cols()).map(|p| delta[(f, p)]).sum::<f32>();
+            }
+
+            let column_error = self.weights.transpose().dot(&delta);
+            let row = &mut input_error.as_mut()
+                [b * channels * height * width..(b + 1) * channels * height * width];
+            self.col2im(&column_error, out, row);
+        }
+
+        input_error
+    }
+
+    fn update(&mut self, optimizer: &Optimizer) {
+        optimizer.update(
+            &mut self.weight_state,
+            &mut self.weights,
+            &mut self.weight_gradient,
+        );
+        optimizer.update(
+            &mut self.bias_state,
+            &mut self.biases,
+            &mut self.bias_gradient,
+        );
+    }
+
+    fn parameters(&self) -> Vec<&Matrix<f32>> {
+        vec![&self.weights, &self.biases]
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Matrix<f32>> {
+        vec![&mut self.weights, &mut self.biases]
+    }
+
+    fn gradients(&self) -> Vec<&Matrix<f32>> {
+        vec![&self.weight_gradient, &self.bias_gradient]
+    }
+
+    fn gradients_mut(&mut self) -> Vec<&mut Matrix<f32>> {
+        vec![&mut self.weight_gradient, &mut self.bias_gradient]
+    }
+
+    fn record(&self, optimizer_state: bool) -> Option<LayerRecord> {
+        let record = LayerRecord::new("conv2d")
+            .attribute("channels", self.input.0)
+            .attribute("height", self.input.1)
+            .attribute("width", self.input.2)
+            .attribute("filters", self.filters);
+        let record = [
+            ("kernel", self.kernel),
+            ("stride", self.stride),
+            ("padding", self.padding),
+            ("dilation", self.dilation),
+        ]
+        .into_iter()
+        .fold(record, |record, (name, (height, width))| {
+            record
+                .attribute(&format!("{name}_height"), height)
+                .attribute(&format!("{name}_width"), width)
+        })
+        .matrix("weights", self.weights.clone())
+        .matrix("biases", self.biases.clone());
+
+        if optimizer_state {
+            Some(
+                record
+                    .optimizer_state("weight_state", &self.weight_state)
+                    .optimizer_state("bias_state", &self.bias_state),
+            )
+        } else {
+            Some(record)
+        }
+    }
+}
+
+/// The number of positions a kernel of `kernel` taps spaced `dilation` apart takes along an
+/// axis of `size` values padded by `padding` on each side, or `None` if it does not fit.
+pub(crate) fn output_size(
+    size: usize,
+    kernel: usize,
+    stride: usize,
+    padding: usize,
+    dilation: usize,
+) -> Option<usize> {
+    let span = dilation * (kernel.max(1) - 1) + 1;
+    (size + 2 * padding)
+        .checked_sub(span)
+        .map(|room| room / stride + 1)
+}
+
+/// Reads an `(height, width)` pair stored as `{name}_height` and `{name}_width`.
+pub(crate) fn get_pair(record: &LayerRecord, name: &str) -> io::Result<(usize, usize)> {
+    Ok((
+        record.get_attribute(&format!("{name}_height"))?,
+        record.get_attribute(&format!("{name}_width"))?,
+    ))
+}
+
+/// Reads a pair like [`get_pair`] whose values must both be greater than zero.
+pub(crate) fn get_nonzero_pair(record: &LayerRecord, name: &str) -> io::Result<(usize, usize)> {
+    let pair = get_pair(record, name)?;
+    if pair.0 == 0 || pair.1 == 0 {
+        return Err(super::record::invalid(format!(
+            "{} layer {name} must be greater than zero, got {pair:?}",
+            record.kind
+        )));
+    }
+
+    Ok(pair)
+}
+
+/// The rows of a `(batch, channels, height, width)` tensor whose samples must have the
+/// `(channels, height, width)` shape `sample`.
+pub(crate) fn image_rows(
+    input: &Tensor<f32>,
+    sample: (usize, usize, usize),
+    layer: &str,
+) -> Matrix<f32> {
+    let dims = &input.shape().dims;
+    if dims.len() != 4 || (dims[1], dims[2], dims[3]) != sample {
+        panic!(
+            "{layer} layer expected a (batch, {}, {}, {}) tensor, got shape {:?}",
+            sample.0, sample.1, sample.2, dims
+        );
+    }
+
+    Matrix::from(input.clone())
+}
+
+/// The inverse of [`image_rows`] for rows of `(channels, height, width)` samples.
+pub(crate) fn image_tensor(rows: Matrix<f32>, sample: (usize, usize, usize)) -> Tensor<f32> {
+    let batch = rows.rows();
+    Tensor::from_vec(
+        rows.as_ref().to_vec(),
+        (batch, sample.0, sample.1, sample.2),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        domain::random_provider,
+        math::{Activation, Loss, Tensor},
+        mlp::{Dense, MultiLayerPerceptron, gradient_check},
+    };
+
+    #[test]
+    fn test_output_size() {
+        assert_eq!(output_size(5, 3, 1, 0, 1), Some(3));
+        assert_eq!(output_size(5, 3, 2, 1, 1), Some(3));
+        assert_eq!(output_size(7, 3, 1, 0, 2), Some(3));
+        assert_eq!(output_size(2, 3, 1, 0, 1), None);
+    }
+
+    #[test]
+    fn test_conv2d_forward() {
+        let image = Tensor::from_vec((1..=16).map(|x| x as f32).collect(), (1, 1, 4, 4));
+        let mut conv = Conv2d::new((1, 4, 4), 2, (2, 2))
+            .stride((2, 2))
+            .bias_initializer(Initializer::Constant(0.5));
+        conv.weights = Matrix::from(vec![vec![1.0, 0.0, 0.0, 1.0], vec![0.25, 0.25, 0.25, 0.25]]);
+
+        let output = conv.forward_tensor(&image);
+        assert_eq!(conv.output_shape(), (2, 2, 2));
+        assert_eq!(output.shape().dims, vec![1, 2, 2, 2]);
+        // The first filter adds the diagonal of each 2x2 block, the second averages it.
+        assert_eq!(output[(0, 0, 0, 0)], 1.0 + 6.0 + 0.5);
+        assert_eq!(output[(0, 0, 1, 1)], 11.0 + 16.0 + 0.5);
+        assert_eq!(output[(0, 1, 0, 1)], (3.0 + 4.0 + 7.0 + 8.0) / 4.0 + 0.5);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Conv2d layer expected a (batch, 1, 4, 4) tensor, got shape [1, 4, 4]"
+    )]
+    fn test_conv2d_forward_tensor_checks_shape() {
+        Conv2d::new((1, 4, 4), 2, (2, 2)).forward_tensor(&Tensor::new((1, 4, 4)));
+    }
+
+    #[test]
+    fn test_conv2d_from_record_rejects_zero_stride() {
+        for name in ["stride_width", "dilation_height"] {
+            let mut record = Conv2d::new((1, 4, 4), 2, (2, 2)).record(false).unwrap();
+            for (key, value) in record.attributes.iter_mut() {
+                if key == name {
+                    *value = "0".to_string();
+                }
+            }
+
+            let error = Conv2d::from_record(&record).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+            assert!(error.to_string().contains("must be greater than zero"));
+        }
+    }
+
+    #[test]
+    fn test_conv2d_padding_and_dilation() {
+        let mut conv = Conv2d::new((1, 3, 3), 1, (3, 3))
+            .padding((1, 1))
+            .dilation((2, 2))
+            .bias_initializer(Initializer::Zeros);
+        conv.weights = Matrix::ones((1, 9));
+        assert_eq!(conv.output_shape(), (1, 1, 1));
+
+        let input = Matrix::from((1..=9).map(|x| x as f32).collect::<Vec<f32>>());
+        // Taps land on rows and columns -1, 1 and 3, so only the center pixel is read.
+        assert_eq!(conv.forward(&input)[(0, 0)], 5.0);
+    }
+
+    #[test]
+    fn test_conv2d_gradient() {
+        random_provider::seed(17);
+
+        let mut model = MultiLayerPerceptron::new()
+            .layer(
+                Conv2d::new((2, 5, 5), 3, (3, 3))
+                    .stride((2, 1))
+                    .padding((1, 1))
+                    .bias_initializer(Initializer::Uniform(-0.5, 0.5)),
+            )
+            .layer(Dense::new((45, 2)).activation(Activation::Tanh));
+
+        let input = Matrix::random((3, 50), -1.0..1.0);
+        let target = Matrix::random((3, 2), -1.0..1.0);
+        let errors = gradient_check(&mut model, &input, &target, &Loss::MSE, 1e-2);
+        for error in errors {
+            assert!(error < 1e-2, "relative error {error}");
+        }
+    }
+}
@@ -1,10 +1,12 @@
-use super::Layer;
+use super::{Layer, LayerRecord};
 use crate::{
     Matrix,
-    math::{Activation, Optimizer},
+    math::{Activation, Initializer, Optimizer, OptimizerState},
 };
+use std::io;
 
 #[derive(PartialEq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Dense {
     shape: (usize, usize),
     activation: Activation,
@@ -12,37 +14,102 @@ pub struct Dense {
     biases: Matrix<f32>,
     weight_gradient: Matrix<f32>,
     bias_gradient: Matrix<f32>,
+    weight_state: OptimizerState,
+    bias_state: OptimizerState,
+    #[cfg_attr(feature = "serde", serde(skip, default = "super::layer::empty_cache"))]
+    pre_activation: Matrix<f32>,
 }
 
 impl Dense {
-    pub fn new(shape: (usize, usize), activation: Activation) -> Self {
+    /// Creates a fully connected layer mapping `shape.0` inputs to `shape.1` outputs. The
+    /// layer is linear unless an activation is set with [`Dense::activation`].
+    pub fn new(shape: (usize, usize)) -> Self {
         let weight_shape = (shape.1, shape.0);
         let bias_shape = (1, shape.1);
 
         Dense {
             shape,
-            activation,
+            activation: Activation::Linear,
             weights: Matrix::random(weight_shape, -1.0..1.0),
             biases: Matrix::random(bias_shape, -1.0..1.0),
             weight_gradient: Matrix::new(weight_shape.0, weight_shape.1),
             bias_gradient: Matrix::new(bias_shape.0, bias_shape.1),
+            weight_state: OptimizerState::new(),
+            bias_state: OptimizerState::new(),
+            pre_activation: Matrix::new(0, shape.1),
         }
     }
-}
 
-impl Layer for Dense {
-    fn feed_forward(&mut self, input: &Matrix<f32>) -> Matrix<f32> {
-        let mut output = Matrix::new(1, self.shape.1);
-        for i in 0..self.shape.1 {
-            let mut sum = self.biases[(0, i)];
-            for j in 0..self.shape.0 {
-                sum += input[(0, j)] * self.weights[(i, j)];
-            }
+    /// Rebuilds a layer from a record written by [`Layer::record`].
+    pub fn from_record(record: &LayerRecord) -> io::Result<Self> {
+        let shape = (
+            record.get_attribute("inputs")?,
+            record.get_attribute("outputs")?,
+        );
+        let weight_shape = (shape.1, shape.0);
+        let bias_shape = (1, shape.1);
+
+        Ok(Dense {
+            shape,
+            activation: record.get_attribute("activation")?,
+            weights: record.get_matrix("weights", weight_shape)?,
+            biases: record.get_matrix("biases", bias_shape)?,
+            weight_gradient: Matrix::new(weight_shape.0, weight_shape.1),
+            bias_gradient: Matrix::new(bias_shape.0, bias_shape.1),
+            weight_state: record.get_optimizer_state("weight_state")?,
+            bias_state: record.get_optimizer_state("bias_state")?,
+            pre_activation: Matrix::new(0, shape.1),
+        })
+    }
+
+    /// The number of inputs and outputs of the layer.
+    pub(crate) fn shape(&self) -> (usize, usize) {
+        self.shape
+    }
+
+    /// Applies `activation` to the layer's output. Use an
+    /// [`ActivationLayer`](super::ActivationLayer) instead to put other layers between the
+    /// affine transform and the nonlinearity.
+    pub fn activation(mut self, activation: Activation) -> Self {
+        self.activation = activation;
+        self
+    }
 
-            output[(0, i)] = self.activation.activate(sum);
+    /// Redraws the weights with the given initializer. The default is uniform in `-1..1`.
+    pub fn weight_initializer(mut self, initializer: Initializer) -> Self {
+        self.weights = initializer.initialize(self.weights.shape(), self.shape.0, self.shape.1);
+        self
+    }
+
+    /// Redraws the biases with the given initializer. The default is uniform in `-1..1`.
+    pub fn bias_initializer(mut self, initializer: Initializer) -> Self {
+        self.biases = initializer.initialize(self.biases.shape(), self.shape.0, self.shape.1);
+        self
+    }
+
+    fn linear(&self, input: &Matrix<f32>) -> Matrix<f32> {
+        if input.cols() != self.shape.0 {
+            panic!(
+                "Dense layer expected {} input features, got {}",
+                self.shape.0,
+                input.cols()
+            );
         }
 
-        output
+        input.dot_transposed(&self.weights) + &self.biases
+    }
+}
+
+impl Layer for Dense {
+    fn forward(&self, input: &Matrix<f32>) -> Matrix<f32> {
+        self.activation.forward(&self.linear(input))
+    }
+
+    fn feed_forward(&mut self, input: &Matrix<f32>) -> Matrix<f32> {
+        let output = self.linear(input);
+        let activated = self.activation.forward(&output);
+        self.pre_activation = output;
+        activated
     }
 
     fn backpropagate(
@@ -51,25 +118,79 @@ impl Layer for Dense {
         prev_input: &Matrix<f32>,
         prev_output: &Matrix<f32>,
     ) -> Matrix<f32> {
-        let mut output_error = Matrix::new(prev_input.shape().0, prev_input.shape().1);
-
-        for i in 0..self.shape.1 {
-            let delta = self.activation.deactivate(prev_output[(0, i)]) * error[(0, i)];
+        let delta = self
+            .activation
+            .backward(&self.pre_activation, prev_output, error);
+        self.backpropagate_delta(&delta, prev_input, prev_output)
+    }
 
-            self.bias_gradient[(0, i)] += delta;
+    fn backpropagate_delta(
+        &mut self,
+        delta: &Matrix<f32>,
+        prev_input: &Matrix<f32>,
+        _: &Matrix<f32>,
+    ) -> Matrix<f32> {
+        self.weight_gradient += delta.transpose_dot(prev_input);
 
-            for j in 0..self.shape.0 {
-                self.weight_gradient[(i, j)] += delta * prev_input[(0, j)];
-                output_error[(0, j)] += self.weights[(i, j)] * error[(0, i)];
+        for i in 0..delta.rows() {
+            for j in 0..delta.cols() {
+                self.bias_gradient[(0, j)] += delta[(i, j)];
             }
         }
 
-        output_error
+        delta.dot(&self.weights)
     }
 
     fn update(&mut self, optimizer: &Optimizer) {
-        optimizer.update(&mut self.weights, &mut self.weight_gradient);
-        optimizer.update(&mut self.biases, &mut self.bias_gradient);
+        optimizer.update(
+            &mut self.weight_state,
+            &mut self.weights,
+            &mut self.weight_gradient,
+        );
+        optimizer.update(
+            &mut self.bias_state,
+            &mut self.biases,
+            &mut self.bias_gradient,
+        );
+    }
+
+    fn activation(&self) -> Option<Activation> {
+        Some(self.activation)
+    }
+
+    fn parameters(&self) -> Vec<&Matrix<f32>> {
+        vec![&self.weights, &self.biases]
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Matrix<f32>> {
+        vec![&mut self.weights, &mut self.biases]
+    }
+
+    fn gradients(&self) -> Vec<&Matrix<f32>> {
+        vec![&self.weight_gradient, &self.bias_gradient]
+    }
+
+    fn gradients_mut(&mut self) -> Vec<&mut Matrix<f32>> {
+        vec![&mut self.weight_gradient, &mut self.bias_gradient]
+    }
+
+    fn record(&self, optimizer_state: bool) -> Option<LayerRecord> {
+        let record = LayerRecord::new("dense")
+            .attribute("inputs", self.shape.0)
+            .attribute("outputs", self.shape.1)
+            .attribute("activation", self.activation)
+            .matrix("weights", self.weights.clone())
+            .matrix("biases", self.biases.clone());
+
+        if optimizer_state {
+            Some(
+                record
+                    .optimizer_state("weight_state", &self.weight_state)
+                    .optimizer_state("bias_state", &self.bias_state),
+            )
+        } else {
+            Some(record)
+        }
     }
 }
 
@@ -82,10 +203,96 @@ mod test {
     fn test_dense() {
         random_provider::seed(42);
 
-        let mut dense = Dense::new((2, 2), Activation::ReLU);
+        let mut dense = Dense::new((2, 2)).activation(Activation::ReLU);
         let input = Matrix::from(vec![vec![1.0, 2.0]]);
         let output = dense.feed_forward(&input);
 
         assert_eq!(output.shape(), (1, 2));
     }
+
+    #[test]
+    fn test_dense_batch_feed_forward() {
+        random_provider::seed(42);
+
+        let mut dense = Dense::new((2, 3));
+        let batch = Matrix::from(vec![vec![1.0, 2.0], vec![-1.0, 0.5], vec![0.0, 3.0]]);
+        let output = dense.feed_forward(&batch);
+
+        assert_eq!(output.shape(), (3, 3));
+        for i in 0..batch.rows() {
+            let row = Matrix::from(vec![batch[(i, 0)], batch[(i, 1)]]);
+            let expected = dense.feed_forward(&row);
+            for j in 0..3 {
+                assert!((output[(i, j)] - expected[(0, j)]).abs() < 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn test_dense_forward_matches_feed_forward() {
+        random_provider::seed(42);
+
+        let mut dense = Dense::new((2, 3)).activation(Activation::Tanh);
+        let batch = Matrix::from(vec![vec![1.0, 2.0], vec![-1.0, 0.5]]);
+
+        let inference = dense.forward(&batch);
+        assert_eq!(dense.pre_activation.rows(), 0);
+        assert_eq!(inference, dense.feed_forward(&batch));
+        assert_eq!(dense.pre_activation.shape(), (2, 3));
+    }
+
+    #[test]
+    fn test_dense_batch_backpropagate() {
+        random_provider::seed(42);
+
+        let mut dense = Dense::new((2, 3));
+        let batch = Matrix::from(vec![vec![1.0, 2.0], vec![-1.0, 0.5]]);
+        let error = Matrix::from(vec![vec![0.1, 0.2, 0.3], vec![-0.3, 0.1, 0.5]]);
+
+        let output = dense.feed_forward(&batch);
+        let input_error = dense.backpropagate(&error, &batch, &output);
+
+        assert_eq!(input_error.shape(), (2, 2));
+        assert_eq!(dense.weight_gradient, error.transpose().dot(&batch));
+        assert!((dense.bias_gradient[(0, 0)] - -0.2).abs() < 1e-6);
+        assert!((dense.bias_gradient[(0, 1)] - 0.3).abs() < 1e-6);
+        assert!((dense.bias_gradient[(0, 2)] - 0.8).abs() < 1e-6);
+        assert_eq!(input_error, error.dot(&dense.weights));
+    }
+
+    #[test]
+    fn test_dense_initializers() {
+        let dense = random_provider::scoped_seed(42, || {
+            Dense::new((16, 8))
+                .activation(Activation::ReLU)
+                .weight_initializer(Initializer::HeUniform)
+                .bias_initializer(Initializer::Zeros)
+        });
+
+        let limit = (6.0f32 / 16.0).sqrt();
+        assert_eq!(dense.weights.shape(), (8, 16));
+        assert!(dense.weights.iter().all(|w| w.abs() <= limit));
+        assert!(dense.biases.iter().all(|&b| b == 0.0));
+
+        let again = random_provider::scoped_seed(42, || {
+            Dense::new((16, 8))
+                .activation(Activation::ReLU)
+                .weight_initializer(Initializer::HeUniform)
+                .bias_initializer(Initializer::Zeros)
+        });
+        assert_eq!(dense.weights, again.weights);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_dense_serde() {
+        random_provider::seed(42);
+
+        let mut dense = Dense::new((2, 3)).activation(Activation::LeakyReLU(0.1));
+        let json = serde_json::to_string(&dense).unwrap();
+        let mut restored = serde_json::from_str::<Dense>(&json).unwrap();
+
+        let input = Matrix::from(vec![vec![1.0, 2.0]]);
+        assert_eq!(restored.feed_forward(&input), dense.feed_forward(&input));
+    }
 }
@@ -0,0 +1,127 @@
+use super::{Layer, LayerRecord};
+use crate::{Matrix, domain::random_provider, math::Optimizer};
+use std::io;
+
+/// Randomly zeroes a fraction `rate` of its inputs during training and scales the rest by
+/// `1 / (1 - rate)`, so the expected activation is unchanged. At inference the layer passes
+/// its input through untouched.
+#[derive(PartialEq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Dropout {
+    rate: f32,
+    #[cfg_attr(feature = "serde", serde(skip, default = "super::layer::empty_cache"))]
+    mask: Matrix<f32>,
+}
+
+impl Dropout {
+    pub fn new(rate: f32) -> Self {
+        if !(0.0..1.0).contains(&rate) {
+            panic!("Dropout rate must be in [0, 1), got {rate}");
+        }
+
+        Dropout {
+            rate,
+            mask: Matrix::new(0, 0),
+        }
+    }
+
+    /// Rebuilds a layer from a record written by [`Layer::record`].
+    pub fn from_record(record: &LayerRecord) -> io::Result<Self> {
+        let rate = record.get_attribute::<f32>("rate")?;
+        if !(0.0..1.0).contains(&rate) {
+            return Err(super::record::invalid(format!(
+                "Invalid dropout rate: {rate}"
+            )));
+        }
+
+        Ok(Dropout::new(rate))
+    }
+
+    pub fn rate(&self) -> f32 {
+        self.rate
+    }
+}
+
+impl Layer for Dropout {
+    fn forward(&self, input: &Matrix<f32>) -> Matrix<f32> {
+        input.clone()
+    }
+
+    fn feed_forward(&mut self, input: &Matrix<f32>) -> Matrix<f32> {
+        let scale = 1.0 / (1.0 - self.rate);
+
+        self.mask = Matrix::zeros(input.shape());
+        let mask = self.mask.as_mut();
+        for i in random_provider::cond_indices(0..input.len(), 1.0 - self.rate) {
+            mask[i] = scale;
+        }
+
+        input * &self.mask
+    }
+
+    fn backpropagate(
+        &mut self,
+        error: &Matrix<f32>,
+        _: &Matrix<f32>,
+        _: &Matrix<f32>,
+    ) -> Matrix<f32> {
+        error * &self.mask
+    }
+
+    fn update(&mut self, _: &Optimizer) {}
+
+    fn record(&self, _: bool) -> Option<LayerRecord> {
+        Some(LayerRecord::new("dropout").attribute("rate", self.rate))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_dropout_training_masks_and_scales() {
+        random_provider::seed(42);
+
+        let mut dropout = Dropout::new(0.25);
+        let input = Matrix::ones((100, 40));
+        let output = dropout.feed_forward(&input);
+
+        let kept = output.iter().filter(|&&x| x != 0.0).count() as f32 / input.len() as f32;
+        assert!((kept - 0.75).abs() < 0.02, "kept: {kept}");
+        assert!(
+            output
+                .iter()
+                .all(|&x| x == 0.0 || (x - 1.0 / 0.75).abs() < 1e-6)
+        );
+
+        let mean = output.iter().sum::<f32>() / output.len() as f32;
+        assert!((mean - 1.0).abs() < 0.03, "mean: {mean}");
+
+        let error = Matrix::ones((100, 40));
+        let input_error = dropout.backpropagate(&error, &input, &output);
+        assert_eq!(input_error, output);
+    }
+
+    #[test]
+    fn test_dropout_is_identity_at_inference() {
+        let dropout = Dropout::new(0.5);
+        let input = Matrix::from(vec![vec![1.0, -2.0, 3.0]]);
+
+        assert_eq!(dropout.forward(&input), input);
+    }
+
+    #[test]
+    fn test_dropout_zero_rate_keeps_everything() {
+        let mut dropout = Dropout::new(0.0);
+        let input = Matrix::from(vec![vec![1.0, -2.0, 3.0]]);
+
+        assert_eq!(dropout.feed_forward(&input), input);
+    }
+
+    #[test]
+    #[should_panic(expected = "Dropout rate must be in [0, 1)")]
+    fn test_dropout_rejects_rate_of_one() {
+        Dropout::new(1.0);
+    }
+}
@@ -0,0 +1,304 @@
+use super::{Layer, LayerRecord};
+use crate::{
+    Matrix,
+    math::{Initializer, Optimizer, OptimizerState},
+};
+use std::{collections::BTreeSet, io};
+
+/// A learned lookup table mapping integer category codes to dense vectors, in place of
+/// one-hot encoding categories by hand. Each input row holds one or more indices stored as
+/// `f32`, and each index is replaced by its `dim` wide row of the table, so a row of `n`
+/// indices becomes `n * dim` outputs laid out one index after another, ready for the
+/// recurrent layers.
+///
+/// Gradients are accumulated sparsely: only the rows looked up since the last update are
+/// touched, and only those rows are handed to the [`Optimizer`]. Every row keeps its own
+/// optimizer state, so stateful optimizers advance a row only when it is used.
+#[derive(PartialEq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Embedding {
+    vocab_size: usize,
+    dim: usize,
+    table: Matrix<f32>,
+    gradient: Matrix<f32>,
+    states: Vec<OptimizerState>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    touched: BTreeSet<usize>,
+}
+
+impl Embedding {
+    /// Creates a table of `vocab_size` rows of `dim` values drawn from a standard normal
+    /// distribution.
+    pub fn new(vocab_size: usize, dim: usize) -> Self {
+        Embedding {
+            vocab_size,
+            dim,
+            table: Initializer::Normal(0.0, 1.0).initialize((vocab_size, dim), vocab_size, dim),
+            gradient: Matrix::zeros((vocab_size, dim)),
+            states: vec![OptimizerState::new(); vocab_size],
+            touched: BTreeSet::new(),
+        }
+    }
+
+    /// Rebuilds a layer from a record written by [`Layer::record`].
+    pub fn from_record(record: &LayerRecord) -> io::Result<Self> {
+        let vocab_size = record.get_attribute("vocab_size")?;
+        let dim = record.get_attribute("dim")?;
+
+        Ok(Embedding {
+            vocab_size,
+            dim,
+            table: record.get_matrix("table", (vocab_size, dim))?,
+            gradient: Matrix::zeros((vocab_size, dim)),
+            states: row_states(record, vocab_size)?,
+            touched: BTreeSet::new(),
+        })
+    }
+
+    /// Redraws the table with the given initializer.
+    pub fn initializer(mut self, initializer: Initializer) -> Self {
+        self.table = initializer.initialize(self.table.shape(), self.vocab_size, self.dim);
+        self
+    }
+
+    /// The learned vectors, one row per category.
+    pub fn table(&self) -> &Matrix<f32> {
+        &self.table
+    }
+
+    fn index(&self, value: f32) -> usize {
+        if value < 0.0 || value.fract() != 0.0 || value as usize >= self.vocab_size {
+            panic!(
+                "Embedding index {value} is not a category in a vocabulary of {}",
+                self.vocab_size
+            );
+        }
+
+        value as usize
+    }
+}
+
+impl Layer for Embedding {
+    fn forward(&self, input: &Matrix<f32>) -> Matrix<f32> {
+        let mut output = Matrix::zeros((input.rows(), input.cols() * self.dim));
+        for i in 0..input.rows() {
+            for t in 0..input.cols() {
+                let row = self.index(input[(i, t)]);
+                for j in 0..self.dim {
+                    output[(i, t * self.dim + j)] = self.table[(row, j)];
+                }
+            }
+        }
+
+        output
+    }
+
+    /// Adds the error of every lookup to its table row. Indices are not differentiable, so
+    /// the error passed back is zero.
+    fn backpropagate(
+        &mut self,
+        error: &Matrix<f32>,
+        prev_input: &Matrix<f32>,
+        _: &Matrix<f32>,
+    ) -> Matrix<f32> {
+        for i in 0..prev_input.rows() {
+            for t in 0..prev_input.cols() {
+                let row = self.index(prev_input[(i, t)]);
+                for j in 0..self.dim {
+                    self.gradient[(row, j)] += error[(i, t * self.dim + j)];
+                }
+                self.touched.insert(row);
+            }
+        }
+
+        Matrix::zeros(prev_input.shape())
+    }
+
+    fn update(&mut self, optimizer: &Optimizer) {
+        let dim = self.dim;
+        for row in std::mem::take(&mut self.touched) {
+            let range = row * dim..(row + 1) * dim;
+            let mut weights = Matrix::from(self.table.as_ref()[range.clone()].to_vec());
+            let mut gradient = Matrix::from(self.gradient.as_ref()[range.clone()].to_vec());
+
+            optimizer.update(&mut self.states[row], &mut weights, &mut gradient);
+            self.table.as_mut()[range.clone()].copy_from_slice(weights.as_ref());
+            self.gradient.as_mut()[range].fill(0.0);
+        }
+    }
+
+    fn parameters(&self) -> Vec<&Matrix<f32>> {
+        vec![&self.table]
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Matrix<f32>> {
+        vec![&mut self.table]
+    }
+
+    fn gradients(&self) -> Vec<&Matrix<f32>> {
+        vec![&self.gradient]
+    }
+
+    fn gradients_mut(&mut self) -> Vec<&mut Matrix<f32>> {
+        vec![&mut self.gradient]
+    }
+
+    fn record(&self, optimizer_state: bool) -> Option<LayerRecord> {
+        let mut record = LayerRecord::new("embedding")
+            .attribute("vocab_size", self.vocab_size)
+            .attribute("dim", self.dim)
+            .matrix("table", self.table.clone());
+
+        if optimizer_state {
+            // Rows that were never trained have a fresh state, which is also what reading
+            // back a missing state gives.
+            for (row, state) in self.states.iter().enumerate() {
+                if state.step() > 0 {
+                    record = record.optimizer_state(&format!("row_state.{row}"), state);
+                }
+            }
+        }
+
+        Some(record)
+    }
+}
+
+/// Reads the optimizer states of the trained rows, written as `row_state.{row}` by
+/// [`Layer::record`], in a single pass over the record. Every other row starts fresh.
+fn row_states(record: &LayerRecord, vocab_size: usize) -> io::Result<Vec<OptimizerState>> {
+    let row = |name: &str| -> io::Result<usize> {
+        name.parse()
+            .ok()
+            .filter(|&row| row < vocab_size)
+            .ok_or_else(|| super::record::invalid(format!("Invalid embedding row state {name}")))
+    };
+
+    let mut steps = vec![None; vocab_size];
+    for (name, value) in &record.attributes {
+        if let Some(name) = name.strip_prefix("row_state.")
+            && let Some(name) = name.strip_suffix(".step")
+        {
+            let step = value.parse().map_err(|_| {
+                super::record::invalid(format!("Invalid embedding row {name} step {value}"))
+            })?;
+            steps[row(name)?] = Some(step);
+        }
+    }
+
+    let mut moments = vec![Vec::new(); vocab_size];
+    for (name, matrix) in &record.matrices {
+        if let Some((name, i)) = name
+            .strip_prefix("row_state.")
+            .and_then(|name| name.split_once('.'))
+        {
+            let i = i.parse::<usize>().map_err(|_| {
+                super::record::invalid(format!("Invalid embedding row {name} moment {i}"))
+            })?;
+            moments[row(name)?].push((i, matrix.clone()));
+        }
+    }
+
+    Ok(steps
+        .into_iter()
+        .zip(moments)
+        .map(|(step, mut moments)| match step {
+            Some(step) => {
+                moments.sort_by_key(|(i, _)| *i);
+                let moments = moments
+                    .into_iter()
+                    .enumerate()
+                    .map_while(|(expected, (i, moment))| (i == expected).then_some(moment))
+                    .collect();
+                OptimizerState::from_parts(step, moments)
+            }
+            None => OptimizerState::new(),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        domain::random_provider,
+        math::{Activation, Loss},
+        mlp::{Dense, MultiLayerPerceptron, gradient_check},
+    };
+
+    #[test]
+    fn test_lookup() {
+        let embedding = Embedding::new(4, 2);
+        let output = embedding.forward(&Matrix::from(vec![vec![3.0, 0.0], vec![1.0, 1.0]]));
+        let table = embedding.table();
+
+        assert_eq!(output.shape(), (2, 4));
+        assert_eq!(output[(0, 0)], table[(3, 0)]);
+        assert_eq!(output[(0, 3)], table[(0, 1)]);
+        assert_eq!(output[(1, 1)], output[(1, 3)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "not a category")]
+    fn test_index_outside_vocabulary() {
+        Embedding::new(4, 2).forward(&Matrix::from(vec![4.0]));
+    }
+
+    #[test]
+    fn test_only_touched_rows_are_updated() {
+        random_provider::seed(2);
+
+        let mut embedding = Embedding::new(5, 3);
+        let before = embedding.table().clone();
+        let input = Matrix::from(vec![vec![1.0, 3.0], vec![3.0, 3.0]]);
+
+        let output = embedding.feed_forward(&input);
+        embedding.backpropagate(&Matrix::ones(output.shape()), &input, &output);
+        assert_eq!(embedding.gradient[(3, 0)], 3.0);
+        assert_eq!(embedding.gradient[(1, 2)], 1.0);
+
+        embedding.update(&Optimizer::adam(0.1));
+        for row in 0..5 {
+            let changed = (0..3).any(|j| embedding.table()[(row, j)] != before[(row, j)]);
+            assert_eq!(changed, row == 1 || row == 3, "row {row}");
+            assert_eq!(embedding.states[row].step(), changed as usize);
+        }
+        assert!(embedding.gradient.iter().all(|&g| g == 0.0));
+    }
+
+    #[test]
+    fn test_embedding_gradients() {
+        random_provider::seed(7);
+
+        let mut model = MultiLayerPerceptron::new()
+            .layer(Embedding::new(6, 3))
+            .layer(Dense::new((6, 2)).activation(Activation::Tanh));
+        let input = Matrix::from(vec![vec![0.0, 5.0], vec![2.0, 2.0], vec![5.0, 1.0]]);
+        let target = Matrix::random((3, 2), -1.0..1.0);
+
+        let errors = gradient_check(&mut model, &input, &target, &Loss::MSE, 1e-2);
+        for error in errors {
+            assert!(error < 1e-2, "relative error {error}");
+        }
+    }
+
+    #[test]
+    fn test_record_keeps_row_states() {
+        random_provider::seed(3);
+
+        let mut embedding = Embedding::new(4, 2);
+        let input = Matrix::from(vec![2.0]);
+        let output = embedding.feed_forward(&input);
+        embedding.backpropagate(&output, &input, &output);
+        embedding.update(&Optimizer::adam(0.01));
+
+        let record = embedding.record(true).unwrap();
+        let next = random_provider::preserve_state(random_provider::random::<u64>);
+        let loaded = Embedding::from_record(&record).unwrap();
+        assert_eq!(loaded, embedding);
+        assert_eq!(random_provider::random::<u64>(), next);
+
+        let record = record.optimizer_state("row_state.4", &embedding.states[2]);
+        let error = Embedding::from_record(&record).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,76 @@
+use super::{Layer, LayerRecord, conv::image_rows};
+use crate::{
+    Matrix,
+    math::{Optimizer, Tensor},
+};
+use std::io;
+
+/// Bridges spatial layers such as [`Conv2d`](super::Conv2d) to [`Dense`](super::Dense).
+/// Spatial layers already store each `(channels, height, width)` sample flattened into a
+/// row, so the values pass through unchanged; the layer checks the sample size and marks
+/// where the network stops treating its features as an image. [`Flatten::forward_tensor`]
+/// turns a batch of images into those rows for training and prediction.
+#[derive(PartialEq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Flatten {
+    input: (usize, usize, usize),
+}
+
+impl Flatten {
+    pub fn new(input: (usize, usize, usize)) -> Self {
+        Flatten { input }
+    }
+
+    /// Rebuilds a layer from a record written by [`Layer::record`].
+    pub fn from_record(record: &LayerRecord) -> io::Result<Self> {
+        Ok(Flatten::new((
+            record.get_attribute("channels")?,
+            record.get_attribute("height")?,
+            record.get_attribute("width")?,
+        )))
+    }
+
+    /// Flattens a `(batch, channels, height, width)` tensor into one sample per row.
+    pub fn forward_tensor(&self, input: &Tensor<f32>) -> Matrix<f32> {
+        image_rows(input, self.input, "Flatten")
+    }
+
+    /// The number of features each sample has after flattening.
+    pub fn outputs(&self) -> usize {
+        self.input.0 * self.input.1 * self.input.2
+    }
+}
+
+impl Layer for Flatten {
+    fn forward(&self, input: &Matrix<f32>) -> Matrix<f32> {
+        if input.cols() != self.outputs() {
+            panic!(
+                "Flatten layer expected samples of shape {:?}, got {} values",
+                self.input,
+                input.cols()
+            );
+        }
+
+        input.clone()
+    }
+
+    fn backpropagate(
+        &mut self,
+        error: &Matrix<f32>,
+        _: &Matrix<f32>,
+        _: &Matrix<f32>,
+    ) -> Matrix<f32> {
+        error.clone()
+    }
+
+    fn update(&mut self, _: &Optimizer) {}
+
+    fn record(&self, _: bool) -> Option<LayerRecord> {
+        Some(
+            LayerRecord::new("flatten")
+                .attribute("channels", self.input.0)
+                .attribute("height", self.input.1)
+                .attribute("width", self.input.2),
+        )
+    }
+}
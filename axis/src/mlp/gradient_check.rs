@@ -0,0 +1,297 @@
+use super::MultiLayerPerceptron;
+use crate::{Matrix, domain::random_provider, math::Loss};
+
+/// Gradients smaller than this are compared absolutely rather than relatively, so f32
+/// rounding noise on near-zero gradients does not dominate the report.
+const GRADIENT_FLOOR: f32 = 1e-2;
+
+/// Checks the analytic gradients of `model` against central finite differences of the loss
+/// on one batch, perturbing every parameter by `epsilon`. Returns the largest relative error
+/// of each layer, in layer order; layers without parameters report zero.
+///
+/// Both the analytic and numeric passes use the training forward pass under the same seed,
+/// so dropout masks match between them. The thread's random sequence is left where it was.
+/// Gradients accumulated before the call are discarded and the gradients are cleared again
+/// before returning, and the parameters and buffers, such as batch normalization
+/// statistics, are restored.
+pub fn gradient_check(
+    model: &mut MultiLayerPerceptron,
+    input: &Matrix<f32>,
+    target: &Matrix<f32>,
+    loss: &Loss,
+    epsilon: f32,
+) -> Vec<f32> {
+    let seed = random_provider::preserve_state(random_provider::random::<u64>);
+    let buffers = model
+        .layers_mut()
+        .iter()
+        .map(|layer| layer.buffers().into_iter().cloned().collect())
+        .collect::<Vec<Vec<Matrix<f32>>>>();
+
+    for layer in model.layers_mut() {
+        layer
+            .gradients_mut()
+            .into_iter()
+            .for_each(|gradient| gradient.fill(0.0));
+    }
+
+    random_provider::scoped_seed(seed, || model.backpropagate_batch(input, target, loss));
+    let analytic = model
+        .layers_mut()
+        .iter_mut()
+        .map(|layer| {
+            layer
+                .gradients_mut()
+                .into_iter()
+                .map(|gradient| {
+                    let copy = gradient.clone();
+                    gradient.fill(0.0);
+                    copy
+                })
+                .collect::<Vec<Matrix<f32>>>()
+        })
+        .collect::<Vec<Vec<Matrix<f32>>>>();
+
+    let objective = |model: &mut MultiLayerPerceptron| {
+        let prediction = random_provider::scoped_seed(seed, || model.feed_forward(input));
+        loss.value(target, &prediction)
+    };
+
+    let mut errors = Vec::with_capacity(analytic.len());
+    for (l, gradients) in analytic.iter().enumerate() {
+        let mut max_error = 0.0f32;
+        for (p, gradient) in gradients.iter().enumerate() {
+            for k in 0..gradient.len() {
+                let original = model.layers_mut()[l].parameters_mut()[p].as_ref()[k];
+
+                model.layers_mut()[l].parameters_mut()[p].as_mut()[k] = original + epsilon;
+                let plus = objective(model);
+                model.layers_mut()[l].parameters_mut()[p].as_mut()[k] = original - epsilon;
+                let minus = objective(model);
+                model.layers_mut()[l].parameters_mut()[p].as_mut()[k] = original;
+
+                let numeric = (plus - minus) / (2.0 * epsilon);
+                let analytic = gradient.as_ref()[k];
+                let scale = numeric.abs().max(analytic.abs()).max(GRADIENT_FLOOR);
+                max_error = max_error.max((numeric - analytic).abs() / scale);
+            }
+        }
+
+        errors.push(max_error);
+    }
+
+    // The training passes above folded the batch statistics into running estimates.
+    for (layer, buffers) in model.layers_mut().iter_mut().zip(buffers) {
+        for (buffer, saved) in layer.buffers_mut().into_iter().zip(buffers) {
+            *buffer = saved;
+        }
+    }
+
+    errors
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        math::{Activation, Initializer},
+        mlp::{ActivationLayer, BatchNorm1d, Dense, Dropout, Layer, LayerNorm},
+    };
+
+    const TOLERANCE: f32 = 1e-2;
+
+    fn batch(features: usize, outputs: usize) -> (Matrix<f32>, Matrix<f32>) {
+        (
+            Matrix::random((6, features), -1.0..1.0),
+            Matrix::random((6, outputs), -1.0..1.0),
+        )
+    }
+
+    fn dense(shape: (usize, usize), activation: Activation) -> Dense {
+        Dense::new(shape)
+            .activation(activation)
+            .weight_initializer(Initializer::XavierUniform)
+    }
+
+    fn assert_within_tolerance(errors: &[f32], name: &str) {
+        for (layer, error) in errors.iter().enumerate() {
+            assert!(
+                *error < TOLERANCE,
+                "{name}: layer {layer} has relative error {error}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_gradient_check_every_activation() {
+        let activations = [
+            Activation::Sigmoid,
+            Activation::ReLU,
+            Activation::LeakyReLU(0.1),
+            Activation::Tanh,
+            Activation::Softmax,
+            Activation::Linear,
+            Activation::GELU,
+            Activation::SiLU,
+            Activation::ELU(1.0),
+            Activation::SELU,
+            Activation::Softplus,
+            Activation::Mish,
+            Activation::HardSigmoid,
+        ];
+
+        for activation in activations {
+            random_provider::scoped_seed(6, || {
+                let (input, target) = batch(3, 2);
+                let mut model = MultiLayerPerceptron::new()
+                    .layer(dense((3, 5), activation))
+                    .layer(dense((5, 2), Activation::Linear));
+
+                let errors = gradient_check(&mut model, &input, &target, &Loss::MSE, 1e-2);
+                assert_eq!(errors.len(), 2);
+                assert_within_tolerance(&errors, &activation.to_string());
+            });
+        }
+    }
+
+    #[test]
+    fn test_gradient_check_every_loss() {
+        for loss in [
+            Loss::MSE,
+            Loss::CrossEntropy,
+            Loss::BinaryCrossEntropy,
+            Loss::Difference,
+            Loss::Hinge,
+            Loss::Huber,
+        ] {
+            random_provider::scoped_seed(34, || {
+                let (input, mut target) = batch(3, 3);
+                let output = match loss {
+                    Loss::CrossEntropy => Activation::Softmax,
+                    Loss::BinaryCrossEntropy => Activation::Sigmoid,
+                    _ => Activation::Linear,
+                };
+                for i in 0..target.rows() {
+                    for j in 0..target.cols() {
+                        target[(i, j)] = match loss {
+                            Loss::CrossEntropy => (j == i % 3) as u8 as f32,
+                            Loss::BinaryCrossEntropy => (target[(i, j)] > 0.0) as u8 as f32,
+                            Loss::Hinge => target[(i, j)].signum(),
+                            Loss::Huber => target[(i, j)] * 3.0,
+                            _ => target[(i, j)],
+                        };
+                    }
+                }
+
+                let mut model = MultiLayerPerceptron::new()
+                    .layer(dense((3, 4), Activation::Tanh))
+                    .layer(dense((4, 3), output));
+
+                let errors = gradient_check(&mut model, &input, &target, &loss, 1e-2);
+                assert_within_tolerance(&errors, &format!("{loss:?}"));
+            });
+        }
+    }
+
+    #[test]
+    fn test_gradient_check_normalization_layers() {
+        random_provider::seed(5);
+
+        let (input, target) = batch(3, 2);
+        let mut model = MultiLayerPerceptron::new()
+            .layer(dense((3, 4), Activation::Linear))
+            .layer(BatchNorm1d::new(4))
+            .layer(ActivationLayer::new(Activation::Tanh))
+            .layer(dense((4, 4), Activation::Linear))
+            .layer(LayerNorm::new(4))
+            .layer(dense((4, 2), Activation::Linear));
+
+        let weights = model.weights();
+        let errors = gradient_check(&mut model, &input, &target, &Loss::MSE, 1e-2);
+        assert_eq!(errors.len(), 6);
+        assert_within_tolerance(&errors, "normalization");
+        assert_eq!(model.weights(), weights);
+    }
+
+    #[test]
+    fn test_gradient_check_detects_wrong_gradients() {
+        random_provider::seed(8);
+
+        let (input, target) = batch(3, 1);
+        let mut model = MultiLayerPerceptron::new()
+            .layer(dense((3, 4), Activation::Tanh))
+            .layer(dense((4, 1), Activation::Linear));
+
+        let errors = gradient_check(&mut model, &input, &target, &Loss::MSE, 1e-2);
+        assert_within_tolerance(&errors, "MSE");
+
+        let mut broken = MultiLayerPerceptron::new().layer(Broken(dense((3, 1), Activation::Tanh)));
+        let errors = gradient_check(&mut broken, &input, &target, &Loss::MSE, 1e-2);
+        assert!(errors[0] > 0.1, "error: {}", errors[0]);
+    }
+
+    #[test]
+    fn test_gradient_check_leaves_model_unchanged() {
+        random_provider::seed(13);
+
+        let (input, target) = batch(3, 2);
+        let mut model = MultiLayerPerceptron::new()
+            .layer(dense((3, 4), Activation::Tanh))
+            .layer(Dropout::new(0.5))
+            .layer(dense((4, 2), Activation::Linear));
+        let weights = model.weights();
+
+        // Stale gradients from an earlier pass must not leak into the analytic ones.
+        model.backpropagate_batch(&input, &target, &Loss::MSE);
+        let next = random_provider::preserve_state(random_provider::random::<u64>);
+
+        let errors = gradient_check(&mut model, &input, &target, &Loss::MSE, 1e-2);
+        assert_eq!(random_provider::random::<u64>(), next);
+        assert_eq!(errors[1], 0.0);
+        assert_within_tolerance(&errors, "Dropout");
+        assert_eq!(model.weights(), weights);
+        assert!(
+            model
+                .layers_mut()
+                .iter()
+                .flat_map(|layer| layer.gradients())
+                .all(|gradient| gradient.iter().all(|&g| g == 0.0))
+        );
+    }
+
+    /// A dense layer whose parameter gradients come out at half their true value, as a
+    /// stand-in for a backprop bug.
+    struct Broken(Dense);
+
+    impl Layer for Broken {
+        fn forward(&self, input: &Matrix<f32>) -> Matrix<f32> {
+            self.0.forward(input)
+        }
+
+        fn feed_forward(&mut self, input: &Matrix<f32>) -> Matrix<f32> {
+            self.0.feed_forward(input)
+        }
+
+        fn backpropagate(
+            &mut self,
+            error: &Matrix<f32>,
+            prev_input: &Matrix<f32>,
+            prev_output: &Matrix<f32>,
+        ) -> Matrix<f32> {
+            self.0
+                .backpropagate(&(error * 0.5), prev_input, prev_output)
+        }
+
+        fn update(&mut self, optimizer: &crate::math::Optimizer) {
+            self.0.update(optimizer);
+        }
+
+        fn parameters_mut(&mut self) -> Vec<&mut Matrix<f32>> {
+            self.0.parameters_mut()
+        }
+
+        fn gradients_mut(&mut self) -> Vec<&mut Matrix<f32>> {
+            self.0.gradients_mut()
+        }
+    }
+}
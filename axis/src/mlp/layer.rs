@@ -1,7 +1,25 @@
-use crate::{Matrix, math::Optimizer};
+use super::LayerRecord;
+use crate::{
+    Matrix,
+    math::{Activation, Optimizer},
+};
 
-pub trait Layer {
-    fn feed_forward(&mut self, input: &Matrix<f32>) -> Matrix<f32>;
+/// A building block of a network. The two forward passes double as the layer's mode:
+/// [`Layer::forward`] is evaluation mode and only borrows the layer, so a trained model can
+/// be shared between threads, while [`Layer::feed_forward`] is training mode and may cache
+/// what the backward pass needs or behave differently, as [`Dropout`](super::Dropout) does.
+pub trait Layer: Send + Sync {
+    fn forward(&self, input: &Matrix<f32>) -> Matrix<f32>;
+
+    /// The training forward pass. Layers that need nothing cached for backpropagation can
+    /// rely on the default, which is [`Layer::forward`].
+    fn feed_forward(&mut self, input: &Matrix<f32>) -> Matrix<f32> {
+        self.forward(input)
+    }
+
+    /// Accumulates the parameter gradients for `error`, taken with respect to the layer's
+    /// output, and returns the error with respect to its input. `prev_input` and
+    /// `prev_output` are the input and output of the matching training forward pass.
     fn backpropagate(
         &mut self,
         error: &Matrix<f32>,
@@ -9,4 +27,64 @@ pub trait Layer {
         prev_output: &Matrix<f32>,
     ) -> Matrix<f32>;
     fn update(&mut self, optimizer: &Optimizer);
+
+    /// The activation applied to the layer's output, if it has one.
+    fn activation(&self) -> Option<Activation> {
+        None
+    }
+
+    /// Backpropagates an error taken with respect to the layer's pre-activation output,
+    /// skipping the activation derivative. This is how fused output gradients, such as
+    /// softmax with cross-entropy, enter the network. Layers without an activation
+    /// backpropagate as usual.
+    fn backpropagate_delta(
+        &mut self,
+        delta: &Matrix<f32>,
+        prev_input: &Matrix<f32>,
+        prev_output: &Matrix<f32>,
+    ) -> Matrix<f32> {
+        self.backpropagate(delta, prev_input, prev_output)
+    }
+
+    /// The trainable parameters of the layer, in a stable order.
+    fn parameters(&self) -> Vec<&Matrix<f32>> {
+        Vec::new()
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Matrix<f32>> {
+        Vec::new()
+    }
+
+    /// State the layer updates while training that the optimizer does not, such as running
+    /// statistics, in a stable order.
+    fn buffers(&self) -> Vec<&Matrix<f32>> {
+        Vec::new()
+    }
+
+    fn buffers_mut(&mut self) -> Vec<&mut Matrix<f32>> {
+        Vec::new()
+    }
+
+    /// The gradients accumulated since the last update, in the same order as
+    /// [`Layer::parameters`].
+    fn gradients(&self) -> Vec<&Matrix<f32>> {
+        Vec::new()
+    }
+
+    fn gradients_mut(&mut self) -> Vec<&mut Matrix<f32>> {
+        Vec::new()
+    }
+
+    /// Describes the layer for saving to a model file, including the optimizer state when
+    /// `optimizer_state` is set. Layers that cannot be saved return `None`.
+    fn record(&self, _optimizer_state: bool) -> Option<LayerRecord> {
+        None
+    }
+}
+
+/// The serde default for a layer's skipped training caches, which the next training forward
+/// pass fills again.
+#[cfg(feature = "serde")]
+pub(crate) fn empty_cache() -> Matrix<f32> {
+    Matrix::new(0, 0)
 }
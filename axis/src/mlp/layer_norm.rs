@@ -0,0 +1,256 @@
+use super::{Layer, LayerRecord};
+use crate::{
+    Matrix,
+    math::{Optimizer, OptimizerState},
+};
+use std::io;
+
+/// Normalizes each sample over its features to zero mean and unit variance, then scales and
+/// shifts it by the learnable `gamma` and `beta`. Unlike [`BatchNorm1d`](super::BatchNorm1d)
+/// it behaves the same in training and at inference.
+#[derive(PartialEq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LayerNorm {
+    features: usize,
+    epsilon: f32,
+    gamma: Matrix<f32>,
+    beta: Matrix<f32>,
+    gamma_gradient: Matrix<f32>,
+    beta_gradient: Matrix<f32>,
+    gamma_state: OptimizerState,
+    beta_state: OptimizerState,
+    #[cfg_attr(feature = "serde", serde(skip, default = "super::layer::empty_cache"))]
+    normalized: Matrix<f32>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    inv_std: Vec<f32>,
+}
+
+impl LayerNorm {
+    pub fn new(features: usize) -> Self {
+        LayerNorm {
+            features,
+            epsilon: 1e-5,
+            gamma: Matrix::ones((1, features)),
+            beta: Matrix::zeros((1, features)),
+            gamma_gradient: Matrix::zeros((1, features)),
+            beta_gradient: Matrix::zeros((1, features)),
+            gamma_state: OptimizerState::new(),
+            beta_state: OptimizerState::new(),
+            normalized: Matrix::new(0, features),
+            inv_std: Vec::new(),
+        }
+    }
+
+    /// Rebuilds a layer from a record written by [`Layer::record`].
+    pub fn from_record(record: &LayerRecord) -> io::Result<Self> {
+        let features = record.get_attribute("features")?;
+        let shape = (1, features);
+
+        Ok(LayerNorm {
+            epsilon: record.get_attribute("epsilon")?,
+            gamma: record.get_matrix("gamma", shape)?,
+            beta: record.get_matrix("beta", shape)?,
+            gamma_state: record.get_optimizer_state("gamma_state")?,
+            beta_state: record.get_optimizer_state("beta_state")?,
+            ..LayerNorm::new(features)
+        })
+    }
+
+    /// Sets the value added to the variance for numerical stability. The default is `1e-5`.
+    pub fn epsilon(mut self, epsilon: f32) -> Self {
+        self.epsilon = epsilon;
+        self
+    }
+
+    /// Returns the normalized input along with the inverse standard deviation of each row.
+    fn normalize(&self, input: &Matrix<f32>) -> (Matrix<f32>, Vec<f32>) {
+        if input.cols() != self.features {
+            panic!(
+                "LayerNorm layer expected {} input features, got {}",
+                self.features,
+                input.cols()
+            );
+        }
+
+        let n = self.features.max(1) as f32;
+        let mut normalized = input.clone();
+        let mut inv_stds = Vec::with_capacity(input.rows());
+        for i in 0..input.rows() {
+            let mean = (0..self.features).map(|j| input[(i, j)]).sum::<f32>() / n;
+            let var = (0..self.features)
+                .map(|j| (input[(i, j)] - mean).powi(2))
+                .sum::<f32>()
+                / n;
+            let inv_std = 1.0 / (var + self.epsilon).sqrt();
+
+            for j in 0..self.features {
+                normalized[(i, j)] = (input[(i, j)] - mean) * inv_std;
+            }
+            inv_stds.push(inv_std);
+        }
+
+        (normalized, inv_stds)
+    }
+
+    fn scale_and_shift(&self, normalized: &Matrix<f32>) -> Matrix<f32> {
+        normalized * &self.gamma + &self.beta
+    }
+}
+
+impl Layer for LayerNorm {
+    fn forward(&self, input: &Matrix<f32>) -> Matrix<f32> {
+        let (normalized, _) = self.normalize(input);
+        self.scale_and_shift(&normalized)
+    }
+
+    fn feed_forward(&mut self, input: &Matrix<f32>) -> Matrix<f32> {
+        let (normalized, inv_std) = self.normalize(input);
+        let output = self.scale_and_shift(&normalized);
+        self.normalized = normalized;
+        self.inv_std = inv_std;
+        output
+    }
+
+    fn backpropagate(
+        &mut self,
+        error: &Matrix<f32>,
+        _: &Matrix<f32>,
+        _: &Matrix<f32>,
+    ) -> Matrix<f32> {
+        let n = self.features.max(1) as f32;
+        let mut input_error = Matrix::zeros(error.shape());
+        for i in 0..error.rows() {
+            let mut error_sum = 0.0;
+            let mut error_dot = 0.0;
+            for j in 0..self.features {
+                let scaled = error[(i, j)] * self.gamma[(0, j)];
+                error_sum += scaled;
+                error_dot += scaled * self.normalized[(i, j)];
+
+                self.gamma_gradient[(0, j)] += error[(i, j)] * self.normalized[(i, j)];
+                self.beta_gradient[(0, j)] += error[(i, j)];
+            }
+
+            let scale = self.inv_std[i] / n;
+            for j in 0..self.features {
+                let scaled = error[(i, j)] * self.gamma[(0, j)];
+                input_error[(i, j)] =
+                    scale * (n * scaled - error_sum - self.normalized[(i, j)] * error_dot);
+            }
+        }
+
+        input_error
+    }
+
+    fn update(&mut self, optimizer: &Optimizer) {
+        optimizer.update(
+            &mut self.gamma_state,
+            &mut self.gamma,
+            &mut self.gamma_gradient,
+        );
+        optimizer.update(
+            &mut self.beta_state,
+            &mut self.beta,
+            &mut self.beta_gradient,
+        );
+    }
+
+    fn parameters(&self) -> Vec<&Matrix<f32>> {
+        vec![&self.gamma, &self.beta]
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Matrix<f32>> {
+        vec![&mut self.gamma, &mut self.beta]
+    }
+
+    fn gradients(&self) -> Vec<&Matrix<f32>> {
+        vec![&self.gamma_gradient, &self.beta_gradient]
+    }
+
+    fn gradients_mut(&mut self) -> Vec<&mut Matrix<f32>> {
+        vec![&mut self.gamma_gradient, &mut self.beta_gradient]
+    }
+
+    fn record(&self, optimizer_state: bool) -> Option<LayerRecord> {
+        let record = LayerRecord::new("layer_norm")
+            .attribute("features", self.features)
+            .attribute("epsilon", self.epsilon)
+            .matrix("gamma", self.gamma.clone())
+            .matrix("beta", self.beta.clone());
+
+        if optimizer_state {
+            Some(
+                record
+                    .optimizer_state("gamma_state", &self.gamma_state)
+                    .optimizer_state("beta_state", &self.beta_state),
+            )
+        } else {
+            Some(record)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_layer_norm_normalizes_rows() {
+        let mut norm = LayerNorm::new(4);
+        let input = Matrix::from(vec![vec![1.0, 2.0, 3.0, 4.0], vec![-5.0, 0.0, 5.0, 10.0]]);
+        let output = norm.feed_forward(&input);
+
+        for i in 0..2 {
+            let mean = (0..4).map(|j| output[(i, j)]).sum::<f32>() / 4.0;
+            let var = (0..4).map(|j| (output[(i, j)] - mean).powi(2)).sum::<f32>() / 4.0;
+            assert!(mean.abs() < 1e-5);
+            assert!((var - 1.0).abs() < 1e-3);
+        }
+
+        assert_eq!(norm.forward(&input), output);
+    }
+
+    #[test]
+    fn test_layer_norm_gradient() {
+        let input = Matrix::from(vec![vec![1.0, -2.0, 0.5], vec![3.0, 0.0, 0.25]]);
+        let weights = Matrix::from(vec![vec![0.3, -1.2, 0.7], vec![1.1, 0.4, -0.5]]);
+        let mut norm = LayerNorm::new(3);
+        norm.gamma = Matrix::from(vec![vec![1.5, 0.5, -1.0]]);
+        norm.beta = Matrix::from(vec![vec![0.1, -0.2, 0.3]]);
+
+        let objective = |input: &Matrix<f32>| {
+            norm.forward(input)
+                .iter()
+                .zip(weights.iter())
+                .map(|(o, w)| o * w)
+                .sum::<f32>()
+        };
+
+        let h = 1e-2;
+        let mut numeric = Matrix::zeros(input.shape());
+        for i in 0..input.rows() {
+            for j in 0..input.cols() {
+                let mut plus = input.clone();
+                plus[(i, j)] += h;
+                let mut minus = input.clone();
+                minus[(i, j)] -= h;
+                numeric[(i, j)] = (objective(&plus) - objective(&minus)) / (2.0 * h);
+            }
+        }
+
+        norm.feed_forward(&input);
+        let input_error = norm.backpropagate(&weights, &input, &input);
+        for (numeric, analytic) in numeric.iter().zip(input_error.iter()) {
+            assert!(
+                (numeric - analytic).abs() < 1e-2,
+                "numeric {numeric}, analytic {analytic}"
+            );
+        }
+
+        let expected = (0..2)
+            .map(|i| weights[(i, 0)] * norm.normalized[(i, 0)])
+            .sum::<f32>();
+        assert!((norm.gamma_gradient[(0, 0)] - expected).abs() < 1e-5);
+        assert!((norm.beta_gradient[(0, 1)] - -0.8).abs() < 1e-6);
+    }
+}
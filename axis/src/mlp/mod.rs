@@ -1,7 +1,35 @@
+pub mod activation_layer;
+pub mod attention;
+pub mod batch_norm;
+pub mod conv;
 pub mod dense;
+pub mod dropout;
+pub mod embedding;
+pub mod flatten;
+pub mod gradient_check;
 pub mod layer;
+pub mod layer_norm;
 pub mod perceptron;
+pub mod pool;
+pub mod record;
+pub mod recurrent;
+pub mod trainer;
+pub mod transformer;
 
+pub use activation_layer::ActivationLayer;
+pub use attention::{MultiHeadAttention, causal_mask, scaled_dot_product_attention};
+pub use batch_norm::BatchNorm1d;
+pub use conv::Conv2d;
 pub use dense::Dense;
+pub use dropout::Dropout;
+pub use embedding::Embedding;
+pub use flatten::Flatten;
+pub use gradient_check::gradient_check;
 pub use layer::Layer;
+pub use layer_norm::LayerNorm;
 pub use perceptron::MultiLayerPerceptron;
+pub use pool::{AvgPool2d, MaxPool2d};
+pub use record::LayerRecord;
+pub use recurrent::{GRU, LSTM, SimpleRNN};
+pub use trainer::{BatchLog, Callback, Control, EpochLog, History, Trainer};
+pub use transformer::TransformerEncoder;
@@ -1,16 +1,31 @@
-use super::Layer;
+use super::{
+    ActivationLayer, AvgPool2d, BatchNorm1d, Conv2d, Dense, Dropout, Embedding, Flatten, GRU,
+    History, LSTM, Layer, LayerNorm, MaxPool2d, MultiHeadAttention, SimpleRNN, Trainer,
+    TransformerEncoder,
+    record::{self, LayerRecord},
+};
 use crate::{
     Matrix,
-    math::{Loss, Optimizer},
+    domain::random_provider,
+    math::{Activation, Loss, Optimizer},
+};
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
 };
 
 pub struct MultiLayerPerceptron {
     layers: Vec<Box<dyn Layer>>,
+    batch_size: Option<usize>,
 }
 
 impl MultiLayerPerceptron {
     pub fn new() -> Self {
-        MultiLayerPerceptron { layers: Vec::new() }
+        MultiLayerPerceptron {
+            layers: Vec::new(),
+            batch_size: None,
+        }
     }
 
     pub fn layer<L: Layer + 'static>(mut self, layer: L) -> Self {
@@ -18,16 +33,133 @@ impl MultiLayerPerceptron {
         self
     }
 
-    pub fn predict(&mut self, input: &Matrix<f32>) -> Matrix<f32> {
-        let mut layer_outputs = Vec::new();
-        let mut current_output = input;
+    /// Sets the number of samples per mini-batch used by [`MultiLayerPerceptron::fit`].
+    /// Without a batch size every call to `fit` performs a single full-batch update.
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        if batch_size == 0 {
+            panic!("Batch size must be greater than zero");
+        }
+
+        self.batch_size = Some(batch_size);
+        self
+    }
+
+    /// Runs inference on a batch whose rows are samples. This only borrows the model, so a
+    /// trained model can serve predictions from several threads at once.
+    pub fn predict(&self, input: &Matrix<f32>) -> Matrix<f32> {
+        self.layers
+            .iter()
+            .fold(input.clone(), |output, layer| layer.forward(&output))
+    }
+
+    /// Computes the mean loss of the model's predictions over the given samples.
+    pub fn evaluate(&self, input: &[Matrix<f32>], target: &[Matrix<f32>], loss: &Loss) -> f32 {
+        if input.is_empty() {
+            return 0.0;
+        }
+
+        let prediction = self.predict(&Matrix::vstack(input));
+        loss.value(&Matrix::vstack(target), &prediction)
+    }
+
+    /// Returns a copy of every layer's parameters followed by its buffers, such as batch
+    /// normalization statistics, in layer order.
+    pub fn weights(&self) -> Vec<Matrix<f32>> {
+        self.layers
+            .iter()
+            .flat_map(|layer| layer.parameters().into_iter().chain(layer.buffers()))
+            .cloned()
+            .collect()
+    }
+
+    /// Overwrites every layer's parameters and buffers with `weights`, which must come from
+    /// [`MultiLayerPerceptron::weights`] on a model with the same architecture.
+    pub fn set_weights(&mut self, weights: Vec<Matrix<f32>>) {
+        let mut weights = weights.into_iter();
+        let mut assign = |parameter: &mut Matrix<f32>| {
+            let weight = weights.next().expect("Not enough weights for the model");
+            if weight.shape() != parameter.shape() {
+                panic!("Matrix dimensions do not match");
+            }
+
+            *parameter = weight;
+        };
+
         for layer in self.layers.iter_mut() {
-            let output = layer.feed_forward(current_output);
-            layer_outputs.push(output);
-            current_output = layer_outputs.last().unwrap();
+            layer.parameters_mut().into_iter().for_each(&mut assign);
+            layer.buffers_mut().into_iter().for_each(&mut assign);
+        }
+
+        if weights.next().is_some() {
+            panic!("Too many weights for the model");
+        }
+    }
+
+    /// Saves the architecture and weights of the model to `path`.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write(&mut writer, false)?;
+        writer.flush()
+    }
+
+    /// Like [`MultiLayerPerceptron::save`], but also saves each layer's optimizer state so
+    /// training can resume exactly where it left off.
+    pub fn save_with_optimizer_state(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write(&mut writer, true)?;
+        writer.flush()
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::read(BufReader::new(File::open(path)?))
+    }
+
+    pub fn write(&self, writer: &mut impl Write, optimizer_state: bool) -> io::Result<()> {
+        let layers = self.records(optimizer_state)?;
+
+        let mut attributes = Vec::new();
+        if let Some(batch_size) = self.batch_size {
+            attributes.push(("batch_size".to_string(), batch_size.to_string()));
+        }
+
+        record::write_model(writer, &attributes, &layers)
+    }
+
+    pub fn read(reader: impl Read) -> io::Result<Self> {
+        let (attributes, records) = record::read_model(BufReader::new(reader))?;
+
+        let mut model = MultiLayerPerceptron::new();
+        for (name, value) in attributes {
+            match name.as_str() {
+                "batch_size" => {
+                    model.batch_size =
+                        Some(value.parse().map_err(|_| {
+                            record::invalid(format!("Invalid batch size: {value}"))
+                        })?);
+                }
+                _ => return Err(record::invalid(format!("Unknown model attribute: {name}"))),
+            }
+        }
+
+        for record in records.iter() {
+            model.layers.push(layer_from_record(record)?);
         }
 
-        current_output.clone()
+        Ok(model)
+    }
+
+    fn records(&self, optimizer_state: bool) -> io::Result<Vec<LayerRecord>> {
+        self.layers
+            .iter()
+            .map(|layer| {
+                layer.record(optimizer_state).ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::Unsupported,
+                        "Model contains a layer that cannot be saved",
+                    )
+                })
+            })
+            .collect()
     }
 
     pub fn fit(
@@ -37,35 +169,668 @@ impl MultiLayerPerceptron {
         optimizer: &Optimizer,
         loss: &Loss,
     ) {
-        for (input, target) in input.iter().zip(target.iter()) {
-            let mut current_output = input;
-
-            let mut layer_outputs = Vec::new();
-            for layer in self.layers.iter_mut() {
-                let output = layer.feed_forward(current_output);
-                layer_outputs.push(output);
-                current_output = layer_outputs.last().unwrap();
+        for batch in self.batches(input.len()) {
+            let input = Matrix::vstack(batch.iter().map(|&sample| &input[sample]));
+            let target = Matrix::vstack(batch.iter().map(|&sample| &target[sample]));
+
+            self.fit_batch(&input, &target, optimizer, loss);
+        }
+    }
+
+    /// Trains for `epochs` passes over the data and returns the per-epoch loss history.
+    /// Use a [`Trainer`] directly to attach callbacks.
+    pub fn fit_epochs(
+        &mut self,
+        input: &[Matrix<f32>],
+        target: &[Matrix<f32>],
+        optimizer: &Optimizer,
+        loss: &Loss,
+        epochs: usize,
+    ) -> History {
+        Trainer::new(*optimizer, *loss)
+            .epochs(epochs)
+            .fit(self, input, target)
+    }
+
+    /// Runs a single forward and backward pass over a batch whose rows are samples, then
+    /// applies one optimizer step with the gradients averaged over the batch. Returns the
+    /// loss of the batch as it was before the update.
+    pub fn fit_batch(
+        &mut self,
+        input: &Matrix<f32>,
+        target: &Matrix<f32>,
+        optimizer: &Optimizer,
+        loss: &Loss,
+    ) -> f32 {
+        let batch_loss = self.backpropagate_batch(input, target, loss);
+        for layer in self.layers.iter_mut() {
+            layer.update(optimizer);
+        }
+
+        batch_loss
+    }
+
+    /// Runs the training forward pass and backpropagates the loss, leaving the gradients,
+    /// averaged over the batch, accumulated in the layers. Returns the loss of the batch.
+    pub(crate) fn backpropagate_batch(
+        &mut self,
+        input: &Matrix<f32>,
+        target: &Matrix<f32>,
+        loss: &Loss,
+    ) -> f32 {
+        // The tape holds every activation of the forward pass: `tape[i]` is the input of
+        // layer `i` and `tape[i + 1]` its output.
+        let mut tape = Vec::with_capacity(self.layers.len() + 1);
+        tape.push(input.clone());
+        for layer in self.layers.iter_mut() {
+            let output = layer.feed_forward(tape.last().unwrap());
+            tape.push(output);
+        }
+
+        // Softmax followed by cross-entropy has the simple, numerically stable gradient
+        // `y_pred - y_true` with respect to the logits, so skip the softmax Jacobian.
+        let fused = *loss == Loss::CrossEntropy
+            && self.layers.last().and_then(|layer| layer.activation()) == Some(Activation::Softmax);
+
+        let prediction = tape.last().unwrap();
+        if target.shape() != prediction.shape() {
+            panic!(
+                "Targets of shape {:?} do not match predictions of shape {:?}",
+                target.shape(),
+                prediction.shape()
+            );
+        }
+
+        let batch_loss = loss.value(target, prediction);
+        let mut error = if fused {
+            (prediction - target) * (1.0 / input.rows() as f32)
+        } else {
+            loss.gradient(target, prediction)
+        };
+
+        let last = self.layers.len().saturating_sub(1);
+        for (idx, layer) in self.layers.iter_mut().enumerate().rev() {
+            let (layer_input, layer_output) = (&tape[idx], &tape[idx + 1]);
+
+            error = if fused && idx == last {
+                layer.backpropagate_delta(&error, layer_input, layer_output)
+            } else {
+                layer.backpropagate(&error, layer_input, layer_output)
+            };
+        }
+
+        batch_loss
+    }
+
+    /// Runs the training forward pass of every layer, as `fit` does before backpropagating.
+    pub(crate) fn feed_forward(&mut self, input: &Matrix<f32>) -> Matrix<f32> {
+        self.layers
+            .iter_mut()
+            .fold(input.clone(), |output, layer| layer.feed_forward(&output))
+    }
+
+    pub(crate) fn layers_mut(&mut self) -> &mut [Box<dyn Layer>] {
+        &mut self.layers
+    }
+
+    /// Shuffles the sample indices and splits them into mini-batches of the configured size.
+    pub(crate) fn batches(&self, samples: usize) -> Vec<Vec<usize>> {
+        let mut indices = (0..samples).collect::<Vec<usize>>();
+        random_provider::shuffle(&mut indices);
+
+        let batch_size = self.batch_size.unwrap_or(samples).max(1);
+        indices
+            .chunks(batch_size)
+            .map(|batch| batch.to_vec())
+            .collect()
+    }
+}
+
+fn layer_from_record(record: &LayerRecord) -> io::Result<Box<dyn Layer>> {
+    match record.kind.as_str() {
+        "dense" => Ok(Box::new(Dense::from_record(record)?)),
+        "activation" => Ok(Box::new(ActivationLayer::from_record(record)?)),
+        "dropout" => Ok(Box::new(Dropout::from_record(record)?)),
+        "batch_norm" => Ok(Box::new(BatchNorm1d::from_record(record)?)),
+        "layer_norm" => Ok(Box::new(LayerNorm::from_record(record)?)),
+        "conv2d" => Ok(Box::new(Conv2d::from_record(record)?)),
+        "max_pool2d" => Ok(Box::new(MaxPool2d::from_record(record)?)),
+        "avg_pool2d" => Ok(Box::new(AvgPool2d::from_record(record)?)),
+        "flatten" => Ok(Box::new(Flatten::from_record(record)?)),
+        "simple_rnn" => Ok(Box::new(SimpleRNN::from_record(record)?)),
+        "lstm" => Ok(Box::new(LSTM::from_record(record)?)),
+        "gru" => Ok(Box::new(GRU::from_record(record)?)),
+        "embedding" => Ok(Box::new(Embedding::from_record(record)?)),
+        "multi_head_attention" => Ok(Box::new(MultiHeadAttention::from_record(record)?)),
+        "transformer_encoder" => Ok(Box::new(TransformerEncoder::from_record(record)?)),
+        kind => Err(record::invalid(format!("Unknown layer kind: {kind}"))),
+    }
+}
+
+/// The serialized form of a model: the same layer records used by the model file format,
+/// including optimizer state.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct ModelData {
+    batch_size: Option<usize>,
+    layers: Vec<LayerRecord>,
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for MultiLayerPerceptron {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::Error;
+
+        let layers = self.records(true).map_err(S::Error::custom)?;
+        ModelData {
+            batch_size: self.batch_size,
+            layers,
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for MultiLayerPerceptron {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::Error;
+
+        let data = ModelData::deserialize(deserializer)?;
+        let layers = data
+            .layers
+            .iter()
+            .map(layer_from_record)
+            .collect::<io::Result<Vec<Box<dyn Layer>>>>()
+            .map_err(D::Error::custom)?;
+
+        Ok(MultiLayerPerceptron {
+            layers,
+            batch_size: data.batch_size,
+        })
+    }
+}
+
+impl Default for MultiLayerPerceptron {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use crate::{domain::random_provider, math::Initializer, mlp::Dense};
+
+    /// The four XOR samples, one per matrix.
+    pub(crate) fn xor() -> (Vec<Matrix<f32>>, Vec<Matrix<f32>>) {
+        let features = vec![
+            Matrix::from(vec![0.0, 0.0]),
+            Matrix::from(vec![0.0, 1.0]),
+            Matrix::from(vec![1.0, 0.0]),
+            Matrix::from(vec![1.0, 1.0]),
+        ];
+        let targets = vec![
+            Matrix::from(vec![0.0]),
+            Matrix::from(vec![1.0]),
+            Matrix::from(vec![1.0]),
+            Matrix::from(vec![0.0]),
+        ];
+
+        (features, targets)
+    }
+
+    /// A seeded `2-16-16-1` network that learns [`xor`].
+    pub(crate) fn xor_mlp() -> MultiLayerPerceptron {
+        random_provider::scoped_seed(4992, || {
+            MultiLayerPerceptron::new()
+                .layer(Dense::new((2, 16)).activation(Activation::Sigmoid))
+                .layer(Dense::new((16, 16)).activation(Activation::ReLU))
+                .layer(Dense::new((16, 1)).activation(Activation::Sigmoid))
+        })
+    }
+
+    fn xor_loss(optimizer: Optimizer, epochs: usize) -> f32 {
+        xor_loss_with(xor_mlp(), optimizer, epochs)
+    }
+
+    fn xor_loss_with(mut mlp: MultiLayerPerceptron, optimizer: Optimizer, epochs: usize) -> f32 {
+        let (features, targets) = xor();
+
+        for _ in 0..epochs {
+            mlp.fit(&features, &targets, &optimizer, &Loss::MSE);
+        }
+
+        features
+            .iter()
+            .zip(targets.iter())
+            .map(|(input, target)| (mlp.predict(input)[(0, 0)] - target[(0, 0)]).powi(2))
+            .sum::<f32>()
+            / features.len() as f32
+    }
+
+    #[test]
+    #[should_panic(expected = "Targets of shape (1, 2) do not match predictions of shape (2, 1)")]
+    fn test_fit_batch_checks_target_shape() {
+        let input = Matrix::from(vec![0.0, 1.0, 1.0, 0.0]).reshape((2, 2));
+        let target = Matrix::from(vec![1.0, 1.0]);
+        xor_mlp().fit_batch(&input, &target, &Optimizer::SGD(0.1), &Loss::MSE);
+    }
+
+    #[test]
+    fn test_adam_converges_faster_than_sgd() {
+        let sgd = xor_loss(Optimizer::SGD(0.03), 200);
+        let adam = xor_loss(Optimizer::adam(0.03), 200);
+
+        assert!(adam < sgd, "adam: {adam}, sgd: {sgd}");
+        assert!(adam < 0.05, "adam: {adam}");
+    }
+
+    #[test]
+    fn test_weights_round_trip() {
+        let (features, targets) = xor();
+        let mut mlp = xor_mlp();
+        let weights = mlp.weights();
+        let before = mlp.evaluate(&features, &targets, &Loss::MSE);
+
+        mlp.fit(&features, &targets, &Optimizer::SGD(0.5), &Loss::MSE);
+        assert_ne!(mlp.weights(), weights);
+
+        mlp.set_weights(weights.clone());
+        assert_eq!(mlp.weights(), weights);
+        assert_eq!(mlp.evaluate(&features, &targets, &Loss::MSE), before);
+    }
+
+    #[test]
+    fn test_concurrent_predict() {
+        let (features, targets) = xor();
+        let mut mlp = xor_mlp();
+        mlp.fit_epochs(&features, &targets, &Optimizer::adam(0.03), &Loss::MSE, 50);
+
+        let expected = mlp.predict(&Matrix::vstack(&features));
+        let mlp = std::sync::Arc::new(mlp);
+        std::thread::scope(|scope| {
+            for (i, input) in features.iter().enumerate() {
+                let mlp = std::sync::Arc::clone(&mlp);
+                let expected = &expected;
+                scope.spawn(move || {
+                    assert_eq!(mlp.predict(input)[(0, 0)], expected[(i, 0)]);
+                });
             }
+        });
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let (features, targets) = xor();
+        let mut mlp = xor_mlp().batch_size(2);
+        mlp.fit_epochs(&features, &targets, &Optimizer::adam(0.01), &Loss::MSE, 10);
+
+        let path = std::env::temp_dir().join(format!("axis-model-{}.txt", std::process::id()));
+        mlp.save(&path).unwrap();
+        let loaded = MultiLayerPerceptron::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.weights(), mlp.weights());
+        assert_eq!(loaded.batch_size, Some(2));
+        for input in features.iter() {
+            assert_eq!(loaded.predict(input), mlp.predict(input));
+        }
+    }
+
+    #[test]
+    fn test_dropout_only_applies_in_training() {
+        let mlp = random_provider::scoped_seed(7, || {
+            MultiLayerPerceptron::new()
+                .layer(Dense::new((2, 8)).activation(Activation::ReLU))
+                .layer(Dropout::new(0.5))
+                .layer(Dense::new((8, 1)).activation(Activation::Sigmoid))
+        });
+
+        let (features, _) = xor();
+        let input = Matrix::vstack(&features);
+        assert_eq!(mlp.predict(&input), mlp.predict(&input));
 
-            let mut error = Matrix::from(loss.apply(target, current_output));
+        let mut buffer = Vec::new();
+        mlp.write(&mut buffer, false).unwrap();
+        let loaded = MultiLayerPerceptron::read(buffer.as_slice()).unwrap();
+        assert_eq!(loaded.predict(&input), mlp.predict(&input));
+    }
+
+    fn normalized_mlp(normalization: impl Layer + 'static) -> MultiLayerPerceptron {
+        random_provider::scoped_seed(3, || {
+            MultiLayerPerceptron::new()
+                .layer(Dense::new((2, 8)).activation(Activation::Tanh))
+                .layer(normalization)
+                .layer(Dense::new((8, 1)).activation(Activation::Sigmoid))
+        })
+    }
+
+    #[test]
+    fn test_normalization_layers_train() {
+        let (features, targets) = xor();
+        for mut mlp in [
+            normalized_mlp(BatchNorm1d::new(8)),
+            normalized_mlp(LayerNorm::new(8)),
+        ] {
+            let before = mlp.weights();
+            let history =
+                mlp.fit_epochs(&features, &targets, &Optimizer::adam(0.05), &Loss::MSE, 300);
+            let after = mlp.weights();
+
+            assert_ne!(before[2], after[2]);
+            assert_ne!(before[3], after[3]);
+            assert!(history.last_loss().unwrap() < history.loss[0] / 2.0);
+
+            let mut buffer = Vec::new();
+            mlp.write(&mut buffer, true).unwrap();
+            let loaded = MultiLayerPerceptron::read(buffer.as_slice()).unwrap();
+            let input = Matrix::vstack(&features);
+            assert_eq!(loaded.predict(&input), mlp.predict(&input));
+        }
+    }
+
+    #[test]
+    fn test_convolutional_model_round_trip() {
+        let mlp = random_provider::scoped_seed(9, || {
+            MultiLayerPerceptron::new()
+                .layer(Conv2d::new((1, 6, 6), 2, (3, 3)).padding((1, 1)))
+                .layer(ActivationLayer::new(Activation::ReLU))
+                .layer(MaxPool2d::new((2, 6, 6), (2, 2)))
+                .layer(AvgPool2d::new((2, 3, 3), (2, 2)).stride((1, 1)))
+                .layer(Flatten::new((2, 2, 2)))
+                .layer(Dense::new((8, 1)).activation(Activation::Sigmoid))
+        });
 
-            let layer_count = self.layers.len();
-            for (idx, layer) in self.layers.iter_mut().rev().enumerate() {
-                let prev_output = &layer_outputs[layer_count - idx - 1];
-                let prev_input = if idx == 0 {
-                    &layer_outputs[idx + 1]
-                } else if idx == layer_count - 1 {
-                    input
-                } else {
-                    &layer_outputs[idx]
-                };
+        let images = crate::math::Tensor::from_vec(
+            random_provider::scoped_seed(10, || Matrix::random((3, 36), 0.0..1.0))
+                .as_ref()
+                .to_vec(),
+            (3, 1, 6, 6),
+        );
+        let input = Matrix::from(images);
+
+        let mut buffer = Vec::new();
+        mlp.write(&mut buffer, false).unwrap();
+        let loaded = MultiLayerPerceptron::read(buffer.as_slice()).unwrap();
+
+        assert_eq!(mlp.predict(&input).shape(), (3, 1));
+        assert_eq!(loaded.predict(&input), mlp.predict(&input));
+    }
 
-                error = layer.backpropagate(&error, prev_input, prev_output);
+    #[test]
+    fn test_convolutional_model_trains() {
+        // Each image is brighter on its left or right half, which the target tells apart.
+        let left = |i: usize| i.is_multiple_of(2);
+        let images = random_provider::scoped_seed(14, || {
+            (0..16)
+                .flat_map(|i| {
+                    (0..36).map(move |p| {
+                        random_provider::range(0.0..0.5)
+                            + ((p % 6 < 3) == left(i)) as u8 as f32 * 0.5
+                    })
+                })
+                .collect::<Vec<f32>>()
+        });
+        let images = crate::math::Tensor::from_vec(images, (16, 1, 6, 6));
+        let targets = (0..16)
+            .map(|i| Matrix::from(vec![left(i) as u8 as f32]))
+            .collect::<Vec<_>>();
+
+        let flatten = Flatten::new((1, 6, 6));
+        let rows = flatten.forward_tensor(&images);
+        let features = (0..rows.rows())
+            .map(|i| Matrix::from(rows.as_ref()[i * 36..(i + 1) * 36].to_vec()))
+            .collect::<Vec<_>>();
+
+        let mut mlp = random_provider::scoped_seed(15, || {
+            MultiLayerPerceptron::new()
+                .layer(Conv2d::new((1, 6, 6), 2, (3, 3)).padding((1, 1)))
+                .layer(ActivationLayer::new(Activation::ReLU))
+                .layer(MaxPool2d::new((2, 6, 6), (2, 2)))
+                .layer(Flatten::new((2, 3, 3)))
+                .layer(Dense::new((18, 1)).activation(Activation::Sigmoid))
+        });
+        let history = random_provider::scoped_seed(16, || {
+            mlp.fit_epochs(&features, &targets, &Optimizer::adam(0.05), &Loss::MSE, 100)
+        });
+
+        assert!(history.last_loss().unwrap() < history.loss[0] / 4.0);
+        let predictions = mlp.predict(&rows);
+        for (prediction, target) in predictions.iter().zip(targets.iter()) {
+            assert_eq!(prediction.round(), target[(0, 0)]);
+        }
+    }
+
+    #[test]
+    fn test_recurrent_model_round_trip() {
+        let mlp = random_provider::scoped_seed(11, || {
+            MultiLayerPerceptron::new()
+                .layer(LSTM::new(2, 4).return_sequences(true))
+                .layer(GRU::new(4, 3).return_sequences(true))
+                .layer(SimpleRNN::new(3, 2))
+                .layer(Dense::new((2, 1)))
+        });
+        let input = random_provider::scoped_seed(12, || Matrix::random((3, 10), -1.0..1.0));
+
+        let mut buffer = Vec::new();
+        mlp.write(&mut buffer, false).unwrap();
+        let loaded = MultiLayerPerceptron::read(buffer.as_slice()).unwrap();
+
+        assert_eq!(mlp.predict(&input).shape(), (3, 1));
+        assert_eq!(loaded.predict(&input), mlp.predict(&input));
+    }
+
+    #[test]
+    fn test_load_resumes_optimizer_state() {
+        let (features, targets) = xor();
+        let optimizer = Optimizer::adam(0.01);
+        let batch = Matrix::vstack(&features);
+        let target = Matrix::vstack(&targets);
+
+        let mut mlp = xor_mlp();
+        mlp.fit_batch(&batch, &target, &optimizer, &Loss::MSE);
+
+        let mut with_state = Vec::new();
+        mlp.write(&mut with_state, true).unwrap();
+        let mut without_state = Vec::new();
+        mlp.write(&mut without_state, false).unwrap();
+
+        let mut resumed = MultiLayerPerceptron::read(with_state.as_slice()).unwrap();
+        let mut restarted = MultiLayerPerceptron::read(without_state.as_slice()).unwrap();
+
+        mlp.fit_batch(&batch, &target, &optimizer, &Loss::MSE);
+        resumed.fit_batch(&batch, &target, &optimizer, &Loss::MSE);
+        restarted.fit_batch(&batch, &target, &optimizer, &Loss::MSE);
+
+        assert_eq!(resumed.weights(), mlp.weights());
+        assert_ne!(restarted.weights(), mlp.weights());
+    }
+
+    #[test]
+    fn test_load_rejects_unknown_layers() {
+        let file = "axis-model 1\nlayer conv9d\nend\n";
+        let error = MultiLayerPerceptron::read(file.as_bytes()).err().unwrap();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_serde() {
+        let (features, targets) = xor();
+        let mut mlp = xor_mlp().batch_size(2);
+        mlp.fit_epochs(&features, &targets, &Optimizer::adam(0.01), &Loss::MSE, 5);
+
+        let json = serde_json::to_string(&mlp).unwrap();
+        let restored = serde_json::from_str::<MultiLayerPerceptron>(&json).unwrap();
+
+        assert_eq!(restored.weights(), mlp.weights());
+        assert_eq!(restored.batch_size, Some(2));
+        for input in features.iter() {
+            assert_eq!(restored.predict(input), mlp.predict(input));
+        }
+    }
+
+    #[test]
+    fn test_mini_batch_converges() {
+        let loss = xor_loss_with(xor_mlp().batch_size(2), Optimizer::adam(0.01), 500);
+
+        assert!(loss < 0.05, "loss: {loss}");
+    }
+
+    #[test]
+    fn test_batch_gradients_are_averaged() {
+        let input = Matrix::from(vec![0.5, -0.25]);
+        let target = Matrix::from(vec![1.0]);
+        let optimizer = Optimizer::SGD(0.1);
+
+        let mut single = xor_mlp();
+        single.fit(
+            std::slice::from_ref(&input),
+            std::slice::from_ref(&target),
+            &optimizer,
+            &Loss::MSE,
+        );
+
+        let mut batched = xor_mlp().batch_size(2);
+        batched.fit(
+            &[input.clone(), input.clone()],
+            &[target.clone(), target.clone()],
+            &optimizer,
+            &Loss::MSE,
+        );
+
+        let expected = single.predict(&input)[(0, 0)];
+        let actual = batched.predict(&input)[(0, 0)];
+        assert!((expected - actual).abs() < 1e-6);
+    }
+
+    /// Compares the gradients `fit_batch` applies against central differences of the loss,
+    /// for a network of `Dense` layers with the given layer sizes.
+    fn assert_gradients_match(sizes: &[usize]) {
+        let build = || {
+            random_provider::scoped_seed(11, || {
+                sizes
+                    .windows(2)
+                    .fold(MultiLayerPerceptron::new(), |mlp, shape| {
+                        mlp.layer(
+                            Dense::new((shape[0], shape[1]))
+                                .activation(Activation::Tanh)
+                                .weight_initializer(Initializer::XavierUniform),
+                        )
+                    })
+            })
+        };
+
+        let (input, target) = random_provider::scoped_seed(5, || {
+            (
+                Matrix::random((6, sizes[0]), -1.0..1.0),
+                Matrix::random((6, 1), -0.5..0.5),
+            )
+        });
+        let inputs = (0..input.rows())
+            .map(|i| {
+                Matrix::from(
+                    (0..input.cols())
+                        .map(|j| input[(i, j)])
+                        .collect::<Vec<f32>>(),
+                )
+            })
+            .collect::<Vec<Matrix<f32>>>();
+        let targets = (0..target.rows())
+            .map(|i| Matrix::from(vec![target[(i, 0)]]))
+            .collect::<Vec<Matrix<f32>>>();
+
+        // A plain SGD step with a learning rate of one leaves `before - after` equal to the
+        // gradient the network computed.
+        let mut trained = build();
+        let before = trained.weights();
+        trained.fit_batch(&input, &target, &Optimizer::SGD(1.0), &Loss::MSE);
+        let after = trained.weights();
+
+        let mut probe = build();
+        let h = 1e-2;
+        for (p, parameter) in before.iter().enumerate() {
+            for k in 0..parameter.len() {
+                let mut weights = before.clone();
+                weights[p].as_mut()[k] += h;
+                probe.set_weights(weights.clone());
+                let plus = probe.evaluate(&inputs, &targets, &Loss::MSE);
+
+                weights[p].as_mut()[k] -= 2.0 * h;
+                probe.set_weights(weights);
+                let minus = probe.evaluate(&inputs, &targets, &Loss::MSE);
+
+                let numeric = (plus - minus) / (2.0 * h);
+                let analytic = before[p].as_ref()[k] - after[p].as_ref()[k];
+                assert!(
+                    (numeric - analytic).abs() <= 1e-3 + 1e-2 * numeric.abs(),
+                    "{} layers, parameter {p}[{k}]: numeric {numeric}, analytic {analytic}",
+                    sizes.len() - 1
+                );
             }
         }
+    }
 
-        for layer in self.layers.iter_mut() {
-            layer.update(optimizer);
+    #[test]
+    fn test_gradients_for_any_depth() {
+        assert_gradients_match(&[3, 1]);
+        assert_gradients_match(&[3, 4, 1]);
+        assert_gradients_match(&[3, 5, 2, 1]);
+        assert_gradients_match(&[4, 6, 3, 5, 2, 1]);
+        assert_gradients_match(&[2, 5, 3, 4, 6, 3, 2, 4, 1]);
+    }
+
+    #[test]
+    fn test_softmax_classification() {
+        let features = vec![
+            Matrix::from(vec![1.0, 0.0, 0.0]),
+            Matrix::from(vec![0.0, 1.0, 0.0]),
+            Matrix::from(vec![0.0, 0.0, 1.0]),
+            Matrix::from(vec![0.9, 0.1, 0.0]),
+            Matrix::from(vec![0.0, 0.8, 0.2]),
+            Matrix::from(vec![0.1, 0.0, 0.9]),
+        ];
+        let targets = vec![
+            Matrix::from(vec![1.0, 0.0, 0.0]),
+            Matrix::from(vec![0.0, 1.0, 0.0]),
+            Matrix::from(vec![0.0, 0.0, 1.0]),
+            Matrix::from(vec![1.0, 0.0, 0.0]),
+            Matrix::from(vec![0.0, 1.0, 0.0]),
+            Matrix::from(vec![0.0, 0.0, 1.0]),
+        ];
+
+        let mut mlp = random_provider::scoped_seed(42, || {
+            MultiLayerPerceptron::new()
+                .layer(Dense::new((3, 8)).activation(Activation::Tanh))
+                .layer(Dense::new((8, 8)).activation(Activation::Tanh))
+                .layer(Dense::new((8, 3)).activation(Activation::Softmax))
+        });
+
+        let history = mlp.fit_epochs(
+            &features,
+            &targets,
+            &Optimizer::adam(0.05),
+            &Loss::CrossEntropy,
+            100,
+        );
+        assert!(history.last_loss().unwrap() < history.loss[0]);
+
+        for (input, target) in features.iter().zip(targets.iter()) {
+            let prediction = mlp.predict(input);
+            let sum = prediction.iter().sum::<f32>();
+            assert!((sum - 1.0).abs() < 1e-5);
+
+            let predicted = (0..3)
+                .max_by(|&a, &b| prediction[(0, a)].total_cmp(&prediction[(0, b)]))
+                .unwrap();
+            assert_eq!(target[(0, predicted)], 1.0);
         }
     }
 }
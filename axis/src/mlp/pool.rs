@@ -0,0 +1,412 @@
+use super::{
+    Layer, LayerRecord,
+    conv::{get_nonzero_pair, image_rows, image_tensor, output_size},
+};
+use crate::{
+    Matrix,
+    math::{Optimizer, Tensor},
+};
+use std::io;
+
+/// The window geometry shared by the pooling layers. Samples are laid out as in
+/// [`Conv2d`](super::Conv2d): `(batch, channels, height, width)` tensors through
+/// `forward_tensor`, or one flattened `(channels, height, width)` sample per row as a
+/// [`Layer`].
+#[derive(PartialEq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Window {
+    input: (usize, usize, usize),
+    kernel: (usize, usize),
+    stride: (usize, usize),
+}
+
+impl Window {
+    fn new(input: (usize, usize, usize), kernel: (usize, usize)) -> Self {
+        if kernel.0 == 0 || kernel.1 == 0 {
+            panic!("Pooling kernel must be greater than zero");
+        }
+
+        Window {
+            input,
+            kernel,
+            stride: kernel,
+        }
+    }
+
+    fn from_record(record: &LayerRecord) -> io::Result<Self> {
+        let window = Window {
+            input: (
+                record.get_attribute("channels")?,
+                record.get_attribute("height")?,
+                record.get_attribute("width")?,
+            ),
+            kernel: get_nonzero_pair(record, "kernel")?,
+            stride: get_nonzero_pair(record, "stride")?,
+        };
+
+        window.try_output_shape().map(|_| window).ok_or_else(|| {
+            super::record::invalid(format!("{} kernel does not fit its input", record.kind))
+        })
+    }
+
+    fn record(&self, kind: &str) -> LayerRecord {
+        LayerRecord::new(kind)
+            .attribute("channels", self.input.0)
+            .attribute("height", self.input.1)
+            .attribute("width", self.input.2)
+            .attribute("kernel_height", self.kernel.0)
+            .attribute("kernel_width", self.kernel.1)
+            .attribute("stride_height", self.stride.0)
+            .attribute("stride_width", self.stride.1)
+    }
+
+    fn try_output_shape(&self) -> Option<(usize, usize, usize)> {
+        Some((
+            self.input.0,
+            output_size(self.input.1, self.kernel.0, self.stride.0, 0, 1)?,
+            output_size(self.input.2, self.kernel.1, self.stride.1, 0, 1)?,
+        ))
+    }
+
+    fn output_shape(&self) -> (usize, usize, usize) {
+        self.try_output_shape().unwrap_or_else(|| {
+            panic!(
+                "Pooling kernel {:?} does not fit input {:?}",
+                self.kernel, self.input
+            )
+        })
+    }
+
+    /// Calls `f(output, inputs)` for every output value of every sample, with the flat
+    /// indices into `input` of the values in its window.
+    fn for_each_window(&self, input: &Matrix<f32>, mut f: impl FnMut(usize, &[usize])) {
+        let (channels, height, width) = self.input;
+        if input.cols() != channels * height * width {
+            panic!(
+                "Pooling layer expected {} input values per sample, got {}",
+                channels * height * width,
+                input.cols()
+            );
+        }
+
+        let (_, out_height, out_width) = self.output_shape();
+        let mut window = Vec::with_capacity(self.kernel.0 * self.kernel.1);
+        let mut output = 0;
+        for b in 0..input.rows() {
+            for c in 0..channels {
+                for oy in 0..out_height {
+                    for ox in 0..out_width {
+                        window.clear();
+                        for ki in 0..self.kernel.0 {
+                            for kj in 0..self.kernel.1 {
+                                let y = oy * self.stride.0 + ki;
+                                let x = ox * self.stride.1 + kj;
+                                window.push(b * input.cols() + (c * height + y) * width + x);
+                            }
+                        }
+
+                        f(output, &window);
+                        output += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    fn output_matrix(&self, rows: usize) -> Matrix<f32> {
+        let (channels, height, width) = self.output_shape();
+        Matrix::zeros((rows, channels * height * width))
+    }
+}
+
+/// Takes the maximum of each `kernel` window of every channel. The stride defaults to the
+/// kernel size, so windows do not overlap.
+#[derive(PartialEq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MaxPool2d {
+    window: Window,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    argmax: Vec<usize>,
+}
+
+impl MaxPool2d {
+    pub fn new(input: (usize, usize, usize), kernel: (usize, usize)) -> Self {
+        MaxPool2d {
+            window: Window::new(input, kernel),
+            argmax: Vec::new(),
+        }
+    }
+
+    /// Rebuilds a layer from a record written by [`Layer::record`].
+    pub fn from_record(record: &LayerRecord) -> io::Result<Self> {
+        Ok(MaxPool2d {
+            window: Window::from_record(record)?,
+            argmax: Vec::new(),
+        })
+    }
+
+    pub fn stride(mut self, stride: (usize, usize)) -> Self {
+        self.window.stride = checked_stride(stride);
+        self
+    }
+
+    /// Like [`Layer::forward`], but on a `(batch, channels, height, width)` tensor.
+    pub fn forward_tensor(&self, input: &Tensor<f32>) -> Tensor<f32> {
+        let output = self.forward(&image_rows(input, self.window.input, "MaxPool2d"));
+        image_tensor(output, self.output_shape())
+    }
+
+    /// The `(channels, height, width)` shape of each output sample.
+    pub fn output_shape(&self) -> (usize, usize, usize) {
+        self.window.output_shape()
+    }
+
+    fn pool(&self, input: &Matrix<f32>) -> (Matrix<f32>, Vec<usize>) {
+        let mut output = self.window.output_matrix(input.rows());
+        let mut argmax = Vec::with_capacity(output.len());
+        let values = input.as_ref();
+
+        self.window.for_each_window(input, |o, window| {
+            let best = window
+                .iter()
+                .copied()
+                .max_by(|&a, &b| values[a].total_cmp(&values[b]))
+                .unwrap();
+            output.as_mut()[o] = values[best];
+            argmax.push(best);
+        });
+
+        (output, argmax)
+    }
+}
+
+impl Layer for MaxPool2d {
+    fn forward(&self, input: &Matrix<f32>) -> Matrix<f32> {
+        self.pool(input).0
+    }
+
+    fn feed_forward(&mut self, input: &Matrix<f32>) -> Matrix<f32> {
+        let (output, argmax) = self.pool(input);
+        self.argmax = argmax;
+        output
+    }
+
+    fn backpropagate(
+        &mut self,
+        error: &Matrix<f32>,
+        prev_input: &Matrix<f32>,
+        _: &Matrix<f32>,
+    ) -> Matrix<f32> {
+        let mut input_error = Matrix::zeros(prev_input.shape());
+        for (&source, &error) in self.argmax.iter().zip(error.iter()) {
+            input_error.as_mut()[source] += error;
+        }
+
+        input_error
+    }
+
+    fn update(&mut self, _: &Optimizer) {}
+
+    fn record(&self, _: bool) -> Option<LayerRecord> {
+        Some(self.window.record("max_pool2d"))
+    }
+}
+
+/// Averages each `kernel` window of every channel. The stride defaults to the kernel size,
+/// so windows do not overlap.
+#[derive(PartialEq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AvgPool2d {
+    window: Window,
+}
+
+impl AvgPool2d {
+    pub fn new(input: (usize, usize, usize), kernel: (usize, usize)) -> Self {
+        AvgPool2d {
+            window: Window::new(input, kernel),
+        }
+    }
+
+    /// Rebuilds a layer from a record written by [`Layer::record`].
+    pub fn from_record(record: &LayerRecord) -> io::Result<Self> {
+        Ok(AvgPool2d {
+            window: Window::from_record(record)?,
+        })
+    }
+
+    pub fn stride(mut self, stride: (usize, usize)) -> Self {
+        self.window.stride = checked_stride(stride);
+        self
+    }
+
+    /// Like [`Layer::forward`], but on a `(batch, channels, height, width)` tensor.
+    pub fn forward_tensor(&self, input: &Tensor<f32>) -> Tensor<f32> {
+        let output = self.forward(&image_rows(input, self.window.input, "AvgPool2d"));
+        image_tensor(output, self.output_shape())
+    }
+
+    /// The `(channels, height, width)` shape of each output sample.
+    pub fn output_shape(&self) -> (usize, usize, usize) {
+        self.window.output_shape()
+    }
+}
+
+impl Layer for AvgPool2d {
+    fn forward(&self, input: &Matrix<f32>) -> Matrix<f32> {
+        let mut output = self.window.output_matrix(input.rows());
+        let values = input.as_ref();
+
+        self.window.for_each_window(input, |o, window| {
+            output.as_mut()[o] =
+                window.iter().map(|&i| values[i]).sum::<f32>() / window.len() as f32;
+        });
+
+        output
+    }
+
+    fn backpropagate(
+        &mut self,
+        error: &Matrix<f32>,
+        prev_input: &Matrix<f32>,
+        _: &Matrix<f32>,
+    ) -> Matrix<f32> {
+        let mut input_error = Matrix::zeros(prev_input.shape());
+        let errors = error.as_ref();
+
+        self.window.for_each_window(prev_input, |o, window| {
+            let share = errors[o] / window.len() as f32;
+            for &i in window {
+                input_error.as_mut()[i] += share;
+            }
+        });
+
+        input_error
+    }
+
+    fn update(&mut self, _: &Optimizer) {}
+
+    fn record(&self, _: bool) -> Option<LayerRecord> {
+        Some(self.window.record("avg_pool2d"))
+    }
+}
+
+fn checked_stride(stride: (usize, usize)) -> (usize, usize) {
+    if stride.0 == 0 || stride.1 == 0 {
+        panic!("Stride must be greater than zero");
+    }
+
+    stride
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        domain::random_provider,
+        math::{Activation, Loss},
+        mlp::{Dense, MultiLayerPerceptron, gradient_check},
+    };
+
+    fn image() -> Matrix<f32> {
+        // Two channels of a 4x4 image, the second one negated.
+        let values = (1..=16).map(|x| x as f32).collect::<Vec<f32>>();
+        let negated = values.iter().map(|x| -x).collect::<Vec<f32>>();
+        Matrix::from([values, negated].concat())
+    }
+
+    #[test]
+    fn test_max_pool() {
+        let mut pool = MaxPool2d::new((2, 4, 4), (2, 2));
+        let output = pool.feed_forward(&image());
+
+        assert_eq!(pool.output_shape(), (2, 2, 2));
+        assert_eq!(
+            output.as_ref(),
+            &[6.0, 8.0, 14.0, 16.0, -1.0, -3.0, -9.0, -11.0]
+        );
+
+        let error = Matrix::from(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0]);
+        let input_error = pool.backpropagate(&error, &image(), &output);
+        assert_eq!(input_error[(0, 5)], 1.0);
+        assert_eq!(input_error[(0, 15)], 4.0);
+        assert_eq!(input_error[(0, 16)], 5.0);
+        assert_eq!(input_error.iter().sum::<f32>(), 36.0);
+    }
+
+    #[test]
+    fn test_pool_forward_tensor() {
+        let images = Tensor::from_vec(image().as_ref().to_vec(), (1, 2, 4, 4));
+        let max = MaxPool2d::new((2, 4, 4), (2, 2)).forward_tensor(&images);
+        let avg = AvgPool2d::new((2, 4, 4), (2, 2)).forward_tensor(&images);
+
+        assert_eq!(max.shape().dims, vec![1, 2, 2, 2]);
+        assert_eq!(max[(0, 0, 1, 1)], 16.0);
+        assert_eq!(max[(0, 1, 0, 1)], -3.0);
+        assert_eq!(avg[(0, 0, 0, 0)], (1.0 + 2.0 + 5.0 + 6.0) / 4.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Pooling kernel must be greater than zero")]
+    fn test_pool_rejects_empty_kernel() {
+        MaxPool2d::new((1, 4, 4), (0, 2));
+    }
+
+    #[test]
+    fn test_pool_from_record_rejects_zero_sizes() {
+        for name in ["kernel_height", "stride_width"] {
+            let mut record = AvgPool2d::new((1, 4, 4), (2, 2)).record(false).unwrap();
+            for (key, value) in record.attributes.iter_mut() {
+                if key == name {
+                    *value = "0".to_string();
+                }
+            }
+
+            let error = AvgPool2d::from_record(&record).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+            assert!(error.to_string().contains("must be greater than zero"));
+        }
+    }
+
+    #[test]
+    fn test_avg_pool_overlapping_windows() {
+        let mut pool = AvgPool2d::new((2, 4, 4), (2, 2)).stride((1, 2));
+        let output = pool.forward(&image());
+
+        assert_eq!(pool.output_shape(), (2, 3, 2));
+        assert_eq!(output[(0, 0)], (1.0 + 2.0 + 5.0 + 6.0) / 4.0);
+        assert_eq!(output[(0, 2)], (5.0 + 6.0 + 9.0 + 10.0) / 4.0);
+        assert_eq!(output[(0, 6)], -output[(0, 0)]);
+
+        let error = Matrix::ones((1, 12));
+        let input_error = pool.backpropagate(&error, &image(), &output);
+        // The middle rows are covered by two windows, the outer rows by one.
+        assert_eq!(input_error[(0, 0)], 0.25);
+        assert_eq!(input_error[(0, 4)], 0.5);
+    }
+
+    #[test]
+    fn test_pool_gradients() {
+        random_provider::seed(29);
+
+        let input = Matrix::random((3, 4), -1.0..1.0);
+        let target = Matrix::random((3, 1), -1.0..1.0);
+
+        // The dense layer in front of the pool checks the gradient the pool passes back. A
+        // small epsilon keeps the perturbations from changing which value wins a window.
+        let mut max = MultiLayerPerceptron::new()
+            .layer(Dense::new((4, 32)))
+            .layer(MaxPool2d::new((2, 4, 4), (2, 2)))
+            .layer(Dense::new((8, 1)).activation(Activation::Tanh));
+        let mut avg = MultiLayerPerceptron::new()
+            .layer(Dense::new((4, 32)))
+            .layer(AvgPool2d::new((2, 4, 4), (3, 3)).stride((1, 1)))
+            .layer(Dense::new((8, 1)).activation(Activation::Tanh));
+
+        for model in [&mut max, &mut avg] {
+            let errors = gradient_check(model, &input, &target, &Loss::MSE, 1e-3);
+            for error in errors {
+                assert!(error < 2e-2, "relative error {error}");
+            }
+        }
+    }
+}
@@ -0,0 +1,361 @@
+use crate::{Matrix, math::OptimizerState};
+use std::{
+    io::{self, BufRead, Write},
+    str::FromStr,
+};
+
+/// The header line of every model file. Bump the version whenever the layout changes in a
+/// way older readers cannot handle.
+pub const MODEL_FORMAT: &str = "axis-model";
+pub const MODEL_FORMAT_VERSION: u32 = 1;
+
+/// Model level `name value` pairs stored ahead of the layers.
+pub type ModelAttributes = Vec<(String, String)>;
+
+/// A self-describing snapshot of a single layer: its kind, scalar attributes such as
+/// shapes and activations, and named parameter matrices.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LayerRecord {
+    pub kind: String,
+    pub attributes: Vec<(String, String)>,
+    pub matrices: Vec<(String, Matrix<f32>)>,
+}
+
+impl LayerRecord {
+    pub fn new(kind: &str) -> Self {
+        LayerRecord {
+            kind: kind.to_string(),
+            attributes: Vec::new(),
+            matrices: Vec::new(),
+        }
+    }
+
+    pub fn attribute(mut self, name: &str, value: impl ToString) -> Self {
+        self.attributes.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn matrix(mut self, name: &str, matrix: Matrix<f32>) -> Self {
+        self.matrices.push((name.to_string(), matrix));
+        self
+    }
+
+    /// Adds the step count and moment buffers of an optimizer state under `name`.
+    pub fn optimizer_state(mut self, name: &str, state: &OptimizerState) -> Self {
+        self = self.attribute(&format!("{name}.step"), state.step());
+        for (i, moment) in state.moments().iter().enumerate() {
+            self = self.matrix(&format!("{name}.{i}"), moment.clone());
+        }
+
+        self
+    }
+
+    /// Adds the attributes and matrices of `record` under `prefix`, for layers built out of
+    /// other layers.
+    pub fn nested(mut self, prefix: &str, record: LayerRecord) -> Self {
+        for (name, value) in record.attributes {
+            self.attributes.push((format!("{prefix}.{name}"), value));
+        }
+        for (name, matrix) in record.matrices {
+            self.matrices.push((format!("{prefix}.{name}"), matrix));
+        }
+
+        self
+    }
+
+    /// Reads back a record of the given kind added with [`LayerRecord::nested`].
+    pub fn get_nested(&self, prefix: &str, kind: &str) -> LayerRecord {
+        let prefix = format!("{prefix}.");
+        let strip = |name: &String| name.strip_prefix(&prefix).map(str::to_string);
+
+        LayerRecord {
+            kind: kind.to_string(),
+            attributes: self
+                .attributes
+                .iter()
+                .filter_map(|(name, value)| Some((strip(name)?, value.clone())))
+                .collect(),
+            matrices: self
+                .matrices
+                .iter()
+                .filter_map(|(name, matrix)| Some((strip(name)?, matrix.clone())))
+                .collect(),
+        }
+    }
+
+    pub fn get_attribute<T>(&self, name: &str) -> io::Result<T>
+    where
+        T: FromStr,
+    {
+        let value = self
+            .attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value)
+            .ok_or_else(|| invalid(format!("{} layer is missing attribute {name}", self.kind)))?;
+
+        value
+            .parse()
+            .map_err(|_| invalid(format!("Invalid value for attribute {name}: {value}")))
+    }
+
+    pub fn get_matrix(&self, name: &str, shape: (usize, usize)) -> io::Result<Matrix<f32>> {
+        let matrix = self
+            .find_matrix(name)
+            .ok_or_else(|| invalid(format!("{} layer is missing matrix {name}", self.kind)))?;
+
+        if matrix.shape() != shape {
+            return Err(invalid(format!(
+                "Matrix {name} has shape {:?}, expected {:?}",
+                matrix.shape(),
+                shape
+            )));
+        }
+
+        Ok(matrix.clone())
+    }
+
+    /// Reads back an optimizer state written with [`LayerRecord::optimizer_state`], or a
+    /// fresh state if none was saved.
+    pub fn get_optimizer_state(&self, name: &str) -> io::Result<OptimizerState> {
+        let step_name = format!("{name}.step");
+        if !self.attributes.iter().any(|(key, _)| *key == step_name) {
+            return Ok(OptimizerState::new());
+        }
+
+        let step = self.get_attribute(&step_name)?;
+        let moments = (0..)
+            .map_while(|i| self.find_matrix(&format!("{name}.{i}")).cloned())
+            .collect();
+
+        Ok(OptimizerState::from_parts(step, moments))
+    }
+
+    fn find_matrix(&self, name: &str) -> Option<&Matrix<f32>> {
+        self.matrices
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, matrix)| matrix)
+    }
+}
+
+/// Writes a model file. The format is line based text: a version header, optional
+/// model attributes, then one block per layer listing its attributes and matrices, with
+/// each matrix row on its own line.
+pub fn write_model(
+    writer: &mut impl Write,
+    attributes: &[(String, String)],
+    layers: &[LayerRecord],
+) -> io::Result<()> {
+    writeln!(writer, "{MODEL_FORMAT} {MODEL_FORMAT_VERSION}")?;
+    for (name, value) in attributes {
+        writeln!(writer, "attr {name} {value}")?;
+    }
+
+    for layer in layers {
+        writeln!(writer, "layer {}", layer.kind)?;
+        for (name, value) in layer.attributes.iter() {
+            writeln!(writer, "attr {name} {value}")?;
+        }
+
+        for (name, matrix) in layer.matrices.iter() {
+            writeln!(writer, "matrix {name} {} {}", matrix.rows(), matrix.cols())?;
+            for i in 0..matrix.rows() {
+                let row = (0..matrix.cols())
+                    .map(|j| matrix[(i, j)].to_string())
+                    .collect::<Vec<String>>();
+                writeln!(writer, "{}", row.join(" "))?;
+            }
+        }
+
+        writeln!(writer, "end")?;
+    }
+
+    Ok(())
+}
+
+/// Reads a model file written by [`write_model`], returning the model attributes and the
+/// layer records in order.
+pub fn read_model(reader: impl BufRead) -> io::Result<(ModelAttributes, Vec<LayerRecord>)> {
+    let mut lines = reader.lines();
+
+    let header = lines
+        .next()
+        .ok_or_else(|| invalid("Model file is empty".to_string()))??;
+    match header.split_once(' ') {
+        Some((MODEL_FORMAT, version)) => {
+            let version = version
+                .trim()
+                .parse::<u32>()
+                .map_err(|_| invalid(format!("Invalid model version: {version}")))?;
+            if version != MODEL_FORMAT_VERSION {
+                return Err(invalid(format!(
+                    "Unsupported model version {version}, expected {MODEL_FORMAT_VERSION}"
+                )));
+            }
+        }
+        _ => return Err(invalid(format!("Not an axis model file: {header}"))),
+    }
+
+    let mut attributes = Vec::new();
+    let mut layers = Vec::new();
+    let mut current: Option<LayerRecord> = None;
+
+    while let Some(line) = lines.next() {
+        let line = line?;
+        let mut parts = line.split_whitespace();
+
+        match parts.next() {
+            None => continue,
+            Some("layer") => {
+                if current.is_some() {
+                    return Err(invalid("Layer is missing its end marker".to_string()));
+                }
+
+                let kind = parts
+                    .next()
+                    .ok_or_else(|| invalid("Layer is missing its kind".to_string()))?;
+                current = Some(LayerRecord::new(kind));
+            }
+            Some("attr") => {
+                let name = parts
+                    .next()
+                    .ok_or_else(|| invalid("Attribute is missing its name".to_string()))?;
+                let value = parts.collect::<Vec<&str>>().join(" ");
+                match current.as_mut() {
+                    Some(layer) => layer.attributes.push((name.to_string(), value)),
+                    None => attributes.push((name.to_string(), value)),
+                }
+            }
+            Some("matrix") => {
+                let layer = current
+                    .as_mut()
+                    .ok_or_else(|| invalid("Matrix outside of a layer".to_string()))?;
+                let name = parts
+                    .next()
+                    .ok_or_else(|| invalid("Matrix is missing its name".to_string()))?;
+                let rows = parse_dim(parts.next())?;
+                let cols = parse_dim(parts.next())?;
+
+                // The dimensions come from the file, so they only bound the values parsed
+                // and never size an allocation up front.
+                let size = rows
+                    .checked_mul(cols)
+                    .ok_or_else(|| invalid(format!("Matrix {name} is too large")))?;
+                let mut data = Vec::new();
+                for _ in 0..rows {
+                    let row = lines
+                        .next()
+                        .ok_or_else(|| invalid(format!("Matrix {name} is truncated")))??;
+                    for value in row.split_whitespace() {
+                        if data.len() == size {
+                            return Err(invalid(format!(
+                                "Matrix {name} has the wrong number of values"
+                            )));
+                        }
+
+                        data.push(
+                            value
+                                .parse::<f32>()
+                                .map_err(|_| invalid(format!("Invalid matrix value: {value}")))?,
+                        );
+                    }
+                }
+
+                if data.len() != size {
+                    return Err(invalid(format!(
+                        "Matrix {name} has the wrong number of values"
+                    )));
+                }
+
+                let matrix = Matrix::from(data).reshape((rows, cols));
+                layer.matrices.push((name.to_string(), matrix));
+            }
+            Some("end") => {
+                let layer = current
+                    .take()
+                    .ok_or_else(|| invalid("End marker outside of a layer".to_string()))?;
+                layers.push(layer);
+            }
+            Some(other) => return Err(invalid(format!("Unexpected entry: {other}"))),
+        }
+    }
+
+    if current.is_some() {
+        return Err(invalid("Layer is missing its end marker".to_string()));
+    }
+
+    Ok((attributes, layers))
+}
+
+pub(crate) fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn parse_dim(value: Option<&str>) -> io::Result<usize> {
+    value
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| invalid("Invalid matrix dimension".to_string()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_record_round_trip() {
+        let record = LayerRecord::new("dense")
+            .attribute("inputs", 2)
+            .attribute("activation", "LeakyReLU(0.01)")
+            .matrix(
+                "weights",
+                Matrix::from(vec![vec![0.1, -2.5e-8], vec![3.0, 1e10]]),
+            )
+            .matrix("biases", Matrix::from(vec![f32::MIN_POSITIVE, -0.0]));
+
+        let mut buffer = Vec::new();
+        write_model(
+            &mut buffer,
+            &[("batch_size".to_string(), "4".to_string())],
+            std::slice::from_ref(&record),
+        )
+        .unwrap();
+
+        let (attributes, layers) = read_model(buffer.as_slice()).unwrap();
+        assert_eq!(
+            attributes,
+            vec![("batch_size".to_string(), "4".to_string())]
+        );
+        assert_eq!(layers, vec![record]);
+        assert_eq!(layers[0].get_attribute::<usize>("inputs").unwrap(), 2);
+    }
+
+    #[test]
+    fn test_read_rejects_other_versions() {
+        let error = read_model("axis-model 99\n".as_bytes()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        let error = read_model("not a model\n".as_bytes()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_read_rejects_truncated_matrix() {
+        let file = "axis-model 1\nlayer dense\nmatrix weights 2 2\n1 2\n";
+        assert!(read_model(file.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_read_rejects_oversized_matrix() {
+        let max = usize::MAX;
+        for file in [
+            format!("axis-model 1\nlayer dense\nmatrix weights {max} {max}\n1\n"),
+            format!("axis-model 1\nlayer dense\nmatrix weights 1 {max}\n1 2\nend\n"),
+            "axis-model 1\nlayer dense\nmatrix weights 1 2\n1 2 3\nend\n".to_string(),
+        ] {
+            let error = read_model(file.as_bytes()).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+}
@@ -0,0 +1,933 @@
+use super::{Layer, LayerRecord};
+use crate::{
+    Matrix,
+    math::{Activation, Initializer, Optimizer, OptimizerState, Tensor},
+};
+use std::io;
+
+/// The weights of a recurrent cell with `gates` stacked gates: the input weights
+/// `(gates * units, features)`, the hidden weights `(gates * units, units)` and the biases
+/// `(1, gates * units)`, along with their gradients and optimizer state.
+#[derive(PartialEq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct RecurrentWeights {
+    features: usize,
+    units: usize,
+    return_sequences: bool,
+    input: Matrix<f32>,
+    hidden: Matrix<f32>,
+    biases: Matrix<f32>,
+    input_gradient: Matrix<f32>,
+    hidden_gradient: Matrix<f32>,
+    bias_gradient: Matrix<f32>,
+    states: [OptimizerState; 3],
+}
+
+impl RecurrentWeights {
+    /// Input weights start Xavier-uniform, hidden weights orthogonal and biases at zero.
+    fn new(features: usize, units: usize, gates: usize) -> Self {
+        let input_shape = (gates * units, features);
+        let hidden_shape = (gates * units, units);
+
+        RecurrentWeights {
+            features,
+            units,
+            return_sequences: false,
+            input: Initializer::XavierUniform.initialize(input_shape, features, units),
+            hidden: Initializer::Orthogonal.initialize(hidden_shape, units, units),
+            biases: Matrix::zeros((1, gates * units)),
+            input_gradient: Matrix::zeros(input_shape),
+            hidden_gradient: Matrix::zeros(hidden_shape),
+            bias_gradient: Matrix::zeros((1, gates * units)),
+            states: Default::default(),
+        }
+    }
+
+    fn from_record(record: &LayerRecord, gates: usize) -> io::Result<Self> {
+        let features = record.get_attribute("features")?;
+        let units = record.get_attribute("units")?;
+        let input_shape = (gates * units, features);
+        let hidden_shape = (gates * units, units);
+
+        Ok(RecurrentWeights {
+            features,
+            units,
+            return_sequences: record.get_attribute("return_sequences")?,
+            input: record.get_matrix("input_weights", input_shape)?,
+            hidden: record.get_matrix("hidden_weights", hidden_shape)?,
+            biases: record.get_matrix("biases", (1, gates * units))?,
+            input_gradient: Matrix::zeros(input_shape),
+            hidden_gradient: Matrix::zeros(hidden_shape),
+            bias_gradient: Matrix::zeros((1, gates * units)),
+            states: [
+                record.get_optimizer_state("input_state")?,
+                record.get_optimizer_state("hidden_state")?,
+                record.get_optimizer_state("bias_state")?,
+            ],
+        })
+    }
+
+    fn record(&self, kind: &str, optimizer_state: bool) -> LayerRecord {
+        let record = LayerRecord::new(kind)
+            .attribute("features", self.features)
+            .attribute("units", self.units)
+            .attribute("return_sequences", self.return_sequences)
+            .matrix("input_weights", self.input.clone())
+            .matrix("hidden_weights", self.hidden.clone())
+            .matrix("biases", self.biases.clone());
+
+        if optimizer_state {
+            record
+                .optimizer_state("input_state", &self.states[0])
+                .optimizer_state("hidden_state", &self.states[1])
+                .optimizer_state("bias_state", &self.states[2])
+        } else {
+            record
+        }
+    }
+
+    /// Runs `forward` on the rows of a `(batch, time, features)` tensor, returning a
+    /// `(batch, time, units)` tensor when returning sequences and `(batch, units)` otherwise.
+    fn forward_tensor(
+        &self,
+        input: &Tensor<f32>,
+        forward: impl FnOnce(&Matrix<f32>) -> Matrix<f32>,
+    ) -> Tensor<f32> {
+        let (batch, time) = match input.shape().dims[..] {
+            [batch, time, features] if features == self.features => (batch, time),
+            _ => panic!(
+                "Recurrent layer expected a (batch, time, {}) tensor, got shape {:?}",
+                self.features,
+                input.shape().dims
+            ),
+        };
+
+        let output = forward(&Matrix::from(input.clone()));
+        if self.return_sequences {
+            Tensor::from_vec(output.as_ref().to_vec(), (batch, time, self.units))
+        } else {
+            Tensor::from_vec(output.as_ref().to_vec(), (batch, self.units))
+        }
+    }
+
+    /// The number of time steps in a batch of flattened `(time, features)` rows.
+    fn steps(&self, input: &Matrix<f32>) -> usize {
+        if self.features == 0 || !input.cols().is_multiple_of(self.features) {
+            panic!(
+                "Recurrent layer expected rows of time steps with {} features, got {} values",
+                self.features,
+                input.cols()
+            );
+        }
+
+        input.cols() / self.features
+    }
+
+    /// Gathers the per-step hidden states into the layer's output: every step when
+    /// returning sequences, otherwise only the last one.
+    fn output(&self, rows: usize, states: &[Matrix<f32>]) -> Matrix<f32> {
+        if self.return_sequences {
+            let mut output = Matrix::zeros((rows, states.len() * self.units));
+            for (t, state) in states.iter().enumerate() {
+                set_step(&mut output, t, state);
+            }
+            output
+        } else {
+            states
+                .last()
+                .cloned()
+                .unwrap_or_else(|| Matrix::zeros((rows, self.units)))
+        }
+    }
+
+    /// The error arriving at the hidden state of step `t` from the layer's output.
+    fn output_error(&self, error: &Matrix<f32>, t: usize, steps: usize) -> Matrix<f32> {
+        if self.return_sequences {
+            step(error, t, self.units)
+        } else if t + 1 == steps {
+            error.clone()
+        } else {
+            Matrix::zeros((error.rows(), self.units))
+        }
+    }
+
+    /// Accumulates the gradients for the gate pre-activation deltas `delta` of one step,
+    /// whose input was `x` and whose hidden weights were applied to `h`, and returns the
+    /// error with respect to `x`.
+    fn accumulate(&mut self, delta: &Matrix<f32>, x: &Matrix<f32>, h: &Matrix<f32>) -> Matrix<f32> {
+        self.input_gradient += &delta.transpose_dot(x);
+        self.hidden_gradient += &delta.transpose_dot(h);
+        for i in 0..delta.rows() {
+            for j in 0..delta.cols() {
+                self.bias_gradient[(0, j)] += delta[(i, j)];
+            }
+        }
+
+        delta.dot(&self.input)
+    }
+
+    fn update(&mut self, optimizer: &Optimizer) {
+        let [input_state, hidden_state, bias_state] = &mut self.states;
+        optimizer.update(input_state, &mut self.input, &mut self.input_gradient);
+        optimizer.update(hidden_state, &mut self.hidden, &mut self.hidden_gradient);
+        optimizer.update(bias_state, &mut self.biases, &mut self.bias_gradient);
+    }
+
+    fn parameters(&self) -> Vec<&Matrix<f32>> {
+        vec![&self.input, &self.hidden, &self.biases]
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Matrix<f32>> {
+        vec![&mut self.input, &mut self.hidden, &mut self.biases]
+    }
+
+    fn gradients(&self) -> Vec<&Matrix<f32>> {
+        vec![
+            &self.input_gradient,
+            &self.hidden_gradient,
+            &self.bias_gradient,
+        ]
+    }
+
+    fn gradients_mut(&mut self) -> Vec<&mut Matrix<f32>> {
+        vec![
+            &mut self.input_gradient,
+            &mut self.hidden_gradient,
+            &mut self.bias_gradient,
+        ]
+    }
+}
+
+/// An Elman recurrent layer, `h_t = tanh(x_t·Wᵀ + h_{t-1}·Uᵀ + b)`, over batches of
+/// `(batch, time, features)` sequences, taken as tensors by [`SimpleRNN::forward_tensor`].
+/// As a [`Layer`], each row holds one sample's time steps one after another, and the
+/// output is the last hidden state or, with [`SimpleRNN::return_sequences`], every hidden
+/// state laid out the same way.
+#[derive(PartialEq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SimpleRNN {
+    weights: RecurrentWeights,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    cache: Vec<RnnStep>,
+}
+
+#[derive(PartialEq, Clone, Debug)]
+struct RnnStep {
+    x: Matrix<f32>,
+    h_prev: Matrix<f32>,
+    h: Matrix<f32>,
+}
+
+impl SimpleRNN {
+    pub fn new(features: usize, units: usize) -> Self {
+        SimpleRNN {
+            weights: RecurrentWeights::new(features, units, 1),
+            cache: Vec::new(),
+        }
+    }
+
+    /// Rebuilds a layer from a record written by [`Layer::record`].
+    pub fn from_record(record: &LayerRecord) -> io::Result<Self> {
+        Ok(SimpleRNN {
+            weights: RecurrentWeights::from_record(record, 1)?,
+            cache: Vec::new(),
+        })
+    }
+
+    /// Outputs the hidden state of every time step instead of only the last one.
+    pub fn return_sequences(mut self, return_sequences: bool) -> Self {
+        self.weights.return_sequences = return_sequences;
+        self
+    }
+
+    /// Like [`Layer::forward`], but on a `(batch, time, features)` tensor, returning a
+    /// `(batch, time, units)` tensor when returning sequences and `(batch, units)` otherwise.
+    pub fn forward_tensor(&self, input: &Tensor<f32>) -> Tensor<f32> {
+        self.weights
+            .forward_tensor(input, |rows| self.forward(rows))
+    }
+
+    fn run(&self, input: &Matrix<f32>) -> (Matrix<f32>, Vec<RnnStep>) {
+        let w = &self.weights;
+        let mut h = Matrix::zeros((input.rows(), w.units));
+        let mut cache = Vec::new();
+
+        for t in 0..w.steps(input) {
+            let x = step(input, t, w.features);
+            let h_next = Activation::Tanh.forward(&affine(w, &x, &h));
+            cache.push(RnnStep {
+                x,
+                h_prev: h,
+                h: h_next.clone(),
+            });
+            h = h_next;
+        }
+
+        let states = cache.iter().map(|s| s.h.clone()).collect::<Vec<_>>();
+        (w.output(input.rows(), &states), cache)
+    }
+}
+
+impl Layer for SimpleRNN {
+    fn forward(&self, input: &Matrix<f32>) -> Matrix<f32> {
+        self.run(input).0
+    }
+
+    fn feed_forward(&mut self, input: &Matrix<f32>) -> Matrix<f32> {
+        let (output, cache) = self.run(input);
+        self.cache = cache;
+        output
+    }
+
+    fn backpropagate(
+        &mut self,
+        error: &Matrix<f32>,
+        prev_input: &Matrix<f32>,
+        _: &Matrix<f32>,
+    ) -> Matrix<f32> {
+        let steps = self.cache.len();
+        let mut input_error = Matrix::zeros(prev_input.shape());
+        let mut dh = Matrix::zeros((error.rows(), self.weights.units));
+
+        for t in (0..steps).rev() {
+            let cached = &self.cache[t];
+            dh += &self.weights.output_error(error, t, steps);
+
+            let delta = dh * map(&cached.h, |h| 1.0 - h * h);
+            let dx = self.weights.accumulate(&delta, &cached.x, &cached.h_prev);
+            set_step(&mut input_error, t, &dx);
+            dh = delta.dot(&self.weights.hidden);
+        }
+
+        input_error
+    }
+
+    fn update(&mut self, optimizer: &Optimizer) {
+        self.weights.update(optimizer);
+    }
+
+    fn parameters(&self) -> Vec<&Matrix<f32>> {
+        self.weights.parameters()
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Matrix<f32>> {
+        self.weights.parameters_mut()
+    }
+
+    fn gradients(&self) -> Vec<&Matrix<f32>> {
+        self.weights.gradients()
+    }
+
+    fn gradients_mut(&mut self) -> Vec<&mut Matrix<f32>> {
+        self.weights.gradients_mut()
+    }
+
+    fn record(&self, optimizer_state: bool) -> Option<LayerRecord> {
+        Some(self.weights.record("simple_rnn", optimizer_state))
+    }
+}
+
+/// A long short-term memory layer over `(batch, time, features)` sequences, laid out as for
+/// [`SimpleRNN`]. The gates are stacked in the order input, forget, cell, output, and the
+/// forget gate bias starts at one so the cell remembers by default.
+#[derive(PartialEq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LSTM {
+    weights: RecurrentWeights,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    cache: Vec<LstmStep>,
+}
+
+#[derive(PartialEq, Clone, Debug)]
+struct LstmStep {
+    x: Matrix<f32>,
+    h_prev: Matrix<f32>,
+    c_prev: Matrix<f32>,
+    i: Matrix<f32>,
+    f: Matrix<f32>,
+    g: Matrix<f32>,
+    o: Matrix<f32>,
+    c_tanh: Matrix<f32>,
+    h: Matrix<f32>,
+}
+
+impl LSTM {
+    pub fn new(features: usize, units: usize) -> Self {
+        let mut weights = RecurrentWeights::new(features, units, 4);
+        for j in units..2 * units {
+            weights.biases[(0, j)] = 1.0;
+        }
+
+        LSTM {
+            weights,
+            cache: Vec::new(),
+        }
+    }
+
+    /// Rebuilds a layer from a record written by [`Layer::record`].
+    pub fn from_record(record: &LayerRecord) -> io::Result<Self> {
+        Ok(LSTM {
+            weights: RecurrentWeights::from_record(record, 4)?,
+            cache: Vec::new(),
+        })
+    }
+
+    /// Outputs the hidden state of every time step instead of only the last one.
+    pub fn return_sequences(mut self, return_sequences: bool) -> Self {
+        self.weights.return_sequences = return_sequences;
+        self
+    }
+
+    /// Like [`Layer::forward`], but on a `(batch, time, features)` tensor, returning a
+    /// `(batch, time, units)` tensor when returning sequences and `(batch, units)` otherwise.
+    pub fn forward_tensor(&self, input: &Tensor<f32>) -> Tensor<f32> {
+        self.weights
+            .forward_tensor(input, |rows| self.forward(rows))
+    }
+
+    fn run(&self, input: &Matrix<f32>) -> (Matrix<f32>, Vec<LstmStep>) {
+        let w = &self.weights;
+        let units = w.units;
+        let mut h = Matrix::zeros((input.rows(), units));
+        let mut c = Matrix::zeros((input.rows(), units));
+        let mut cache = Vec::new();
+
+        for t in 0..w.steps(input) {
+            let x = step(input, t, w.features);
+            let gates = affine(w, &x, &h);
+            let i = Activation::Sigmoid.forward(&columns(&gates, 0, units));
+            let f = Activation::Sigmoid.forward(&columns(&gates, units, units));
+            let g = Activation::Tanh.forward(&columns(&gates, 2 * units, units));
+            let o = Activation::Sigmoid.forward(&columns(&gates, 3 * units, units));
+
+            let c_next = &f * &c + &(&i * &g);
+            let c_tanh = Activation::Tanh.forward(&c_next);
+            let h_next = &o * &c_tanh;
+
+            cache.push(LstmStep {
+                x,
+                h_prev: h,
+                c_prev: c,
+                i,
+                f,
+                g,
+                o,
+                c_tanh,
+                h: h_next.clone(),
+            });
+            h = h_next;
+            c = c_next;
+        }
+
+        let states = cache.iter().map(|s| s.h.clone()).collect::<Vec<_>>();
+        (w.output(input.rows(), &states), cache)
+    }
+}
+
+impl Layer for LSTM {
+    fn forward(&self, input: &Matrix<f32>) -> Matrix<f32> {
+        self.run(input).0
+    }
+
+    fn feed_forward(&mut self, input: &Matrix<f32>) -> Matrix<f32> {
+        let (output, cache) = self.run(input);
+        self.cache = cache;
+        output
+    }
+
+    fn backpropagate(
+        &mut self,
+        error: &Matrix<f32>,
+        prev_input: &Matrix<f32>,
+        _: &Matrix<f32>,
+    ) -> Matrix<f32> {
+        let steps = self.cache.len();
+        let units = self.weights.units;
+        let mut input_error = Matrix::zeros(prev_input.shape());
+        let mut dh = Matrix::<f32>::zeros((error.rows(), units));
+        let mut dc = Matrix::<f32>::zeros((error.rows(), units));
+
+        for t in (0..steps).rev() {
+            let s = &self.cache[t];
+            dh += &self.weights.output_error(error, t, steps);
+
+            dc += &(&dh * &s.o * map(&s.c_tanh, |c| 1.0 - c * c));
+            let d_o = dh * &s.c_tanh;
+            let d_i = &dc * &s.g;
+            let d_f = &dc * &s.c_prev;
+            let d_g = &dc * &s.i;
+
+            let delta = concat_columns(&[
+                d_i * map(&s.i, sigmoid_derivative),
+                d_f * map(&s.f, sigmoid_derivative),
+                d_g * map(&s.g, |g| 1.0 - g * g),
+                d_o * map(&s.o, sigmoid_derivative),
+            ]);
+
+            let dx = self.weights.accumulate(&delta, &s.x, &s.h_prev);
+            set_step(&mut input_error, t, &dx);
+            dh = delta.dot(&self.weights.hidden);
+            dc *= &s.f;
+        }
+
+        input_error
+    }
+
+    fn update(&mut self, optimizer: &Optimizer) {
+        self.weights.update(optimizer);
+    }
+
+    fn parameters(&self) -> Vec<&Matrix<f32>> {
+        self.weights.parameters()
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Matrix<f32>> {
+        self.weights.parameters_mut()
+    }
+
+    fn gradients(&self) -> Vec<&Matrix<f32>> {
+        self.weights.gradients()
+    }
+
+    fn gradients_mut(&mut self) -> Vec<&mut Matrix<f32>> {
+        self.weights.gradients_mut()
+    }
+
+    fn record(&self, optimizer_state: bool) -> Option<LayerRecord> {
+        Some(self.weights.record("lstm", optimizer_state))
+    }
+}
+
+/// A gated recurrent unit layer over `(batch, time, features)` sequences, laid out as for
+/// [`SimpleRNN`]. The gates are stacked in the order update, reset, candidate, and the
+/// reset gate is applied to the hidden state before its weights:
+/// `n_t = tanh(x_t·W_nᵀ + (r_t * h_{t-1})·U_nᵀ + b_n)` and
+/// `h_t = (1 - z_t) * n_t + z_t * h_{t-1}`.
+#[derive(PartialEq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GRU {
+    weights: RecurrentWeights,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    cache: Vec<GruStep>,
+}
+
+#[derive(PartialEq, Clone, Debug)]
+struct GruStep {
+    x: Matrix<f32>,
+    h_prev: Matrix<f32>,
+    z: Matrix<f32>,
+    r: Matrix<f32>,
+    n: Matrix<f32>,
+    h: Matrix<f32>,
+}
+
+impl GRU {
+    pub fn new(features: usize, units: usize) -> Self {
+        GRU {
+            weights: RecurrentWeights::new(features, units, 3),
+            cache: Vec::new(),
+        }
+    }
+
+    /// Rebuilds a layer from a record written by [`Layer::record`].
+    pub fn from_record(record: &LayerRecord) -> io::Result<Self> {
+        Ok(GRU {
+            weights: RecurrentWeights::from_record(record, 3)?,
+            cache: Vec::new(),
+        })
+    }
+
+    /// Outputs the hidden state of every time step instead of only the last one.
+    pub fn return_sequences(mut self, return_sequences: bool) -> Self {
+        self.weights.return_sequences = return_sequences;
+        self
+    }
+
+    /// Like [`Layer::forward`], but on a `(batch, time, features)` tensor, returning a
+    /// `(batch, time, units)` tensor when returning sequences and `(batch, units)` otherwise.
+    pub fn forward_tensor(&self, input: &Tensor<f32>) -> Tensor<f32> {
+        self.weights
+            .forward_tensor(input, |rows| self.forward(rows))
+    }
+
+    fn run(&self, input: &Matrix<f32>) -> (Matrix<f32>, Vec<GruStep>) {
+        let w = &self.weights;
+        let units = w.units;
+        let gate_weights = rows(&w.hidden, 0, 2 * units);
+        let candidate_weights = rows(&w.hidden, 2 * units, units);
+        let mut h = Matrix::zeros((input.rows(), units));
+        let mut cache = Vec::new();
+
+        for t in 0..w.steps(input) {
+            let x = step(input, t, w.features);
+            let projected = x.dot_transposed(&w.input) + &w.biases;
+
+            let gates = columns(&projected, 0, 2 * units) + h.dot_transposed(&gate_weights);
+            let z = Activation::Sigmoid.forward(&columns(&gates, 0, units));
+            let r = Activation::Sigmoid.forward(&columns(&gates, units, units));
+            let n = Activation::Tanh.forward(
+                &(columns(&projected, 2 * units, units)
+                    + (&r * &h).dot_transposed(&candidate_weights)),
+            );
+
+            let h_next = map(&z, |z| 1.0 - z) * &n + &(&z * &h);
+            cache.push(GruStep {
+                x,
+                h_prev: h,
+                z,
+                r,
+                n,
+                h: h_next.clone(),
+            });
+            h = h_next;
+        }
+
+        let states = cache.iter().map(|s| s.h.clone()).collect::<Vec<_>>();
+        (w.output(input.rows(), &states), cache)
+    }
+}
+
+impl Layer for GRU {
+    fn forward(&self, input: &Matrix<f32>) -> Matrix<f32> {
+        self.run(input).0
+    }
+
+    fn feed_forward(&mut self, input: &Matrix<f32>) -> Matrix<f32> {
+        let (output, cache) = self.run(input);
+        self.cache = cache;
+        output
+    }
+
+    fn backpropagate(
+        &mut self,
+        error: &Matrix<f32>,
+        prev_input: &Matrix<f32>,
+        _: &Matrix<f32>,
+    ) -> Matrix<f32> {
+        let steps = self.cache.len();
+        let units = self.weights.units;
+        let gate_weights = rows(&self.weights.hidden, 0, 2 * units);
+        let candidate_weights = rows(&self.weights.hidden, 2 * units, units);
+        let mut input_error = Matrix::zeros(prev_input.shape());
+        let mut dh = Matrix::<f32>::zeros((error.rows(), units));
+
+        for t in (0..steps).rev() {
+            let s = &self.cache[t];
+            dh += &self.weights.output_error(error, t, steps);
+
+            let d_z = &dh * &(&s.h_prev - &s.n);
+            let d_n = &dh * &map(&s.z, |z| 1.0 - z);
+            let mut dh_prev = dh * &s.z;
+
+            let delta_n = d_n * map(&s.n, |n| 1.0 - n * n);
+            let reset_hidden = &s.r * &s.h_prev;
+            let d_reset_hidden = delta_n.dot(&candidate_weights);
+            let d_r = &d_reset_hidden * &s.h_prev;
+            dh_prev += d_reset_hidden * &s.r;
+
+            let delta_gates = concat_columns(&[
+                d_z * map(&s.z, sigmoid_derivative),
+                d_r * map(&s.r, sigmoid_derivative),
+            ]);
+            dh_prev += delta_gates.dot(&gate_weights);
+
+            // The candidate's hidden weights see `r * h` rather than `h`, so accumulate the
+            // gate and candidate halves of the hidden gradient separately.
+            let delta = concat_columns(&[delta_gates.clone(), delta_n.clone()]);
+            let hidden_gradient = Matrix::vstack([
+                &delta_gates.transpose_dot(&s.h_prev),
+                &delta_n.transpose_dot(&reset_hidden),
+            ]);
+            let w = &mut self.weights;
+            w.input_gradient += delta.transpose_dot(&s.x);
+            w.hidden_gradient += hidden_gradient;
+            for i in 0..delta.rows() {
+                for j in 0..delta.cols() {
+                    w.bias_gradient[(0, j)] += delta[(i, j)];
+                }
+            }
+
+            set_step(&mut input_error, t, &delta.dot(&w.input));
+            dh = dh_prev;
+        }
+
+        input_error
+    }
+
+    fn update(&mut self, optimizer: &Optimizer) {
+        self.weights.update(optimizer);
+    }
+
+    fn parameters(&self) -> Vec<&Matrix<f32>> {
+        self.weights.parameters()
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Matrix<f32>> {
+        self.weights.parameters_mut()
+    }
+
+    fn gradients(&self) -> Vec<&Matrix<f32>> {
+        self.weights.gradients()
+    }
+
+    fn gradients_mut(&mut self) -> Vec<&mut Matrix<f32>> {
+        self.weights.gradients_mut()
+    }
+
+    fn record(&self, optimizer_state: bool) -> Option<LayerRecord> {
+        Some(self.weights.record("gru", optimizer_state))
+    }
+}
+
+/// `x·Wᵀ + h·Uᵀ + b` for every gate at once.
+fn affine(weights: &RecurrentWeights, x: &Matrix<f32>, h: &Matrix<f32>) -> Matrix<f32> {
+    x.dot_transposed(&weights.input) + h.dot_transposed(&weights.hidden) + &weights.biases
+}
+
+fn map(matrix: &Matrix<f32>, f: impl Fn(f32) -> f32) -> Matrix<f32> {
+    let mut output = matrix.clone();
+    for value in output.iter_mut() {
+        *value = f(*value);
+    }
+
+    output
+}
+
+fn sigmoid_derivative(s: f32) -> f32 {
+    s * (1.0 - s)
+}
+
+/// The `width` columns of `matrix` starting at `start`.
+fn columns(matrix: &Matrix<f32>, start: usize, width: usize) -> Matrix<f32> {
+    let mut output = Matrix::zeros((matrix.rows(), width));
+    for i in 0..matrix.rows() {
+        for j in 0..width {
+            output[(i, j)] = matrix[(i, start + j)];
+        }
+    }
+
+    output
+}
+
+/// The `count` rows of `matrix` starting at `start`.
+fn rows(matrix: &Matrix<f32>, start: usize, count: usize) -> Matrix<f32> {
+    let cols = matrix.cols();
+    Matrix::from(matrix.as_ref()[start * cols..(start + count) * cols].to_vec())
+        .reshape((count, cols))
+}
+
+fn concat_columns(matrices: &[Matrix<f32>]) -> Matrix<f32> {
+    let rows = matrices.first().map_or(0, |m| m.rows());
+    let cols = matrices.iter().map(|m| m.cols()).sum();
+    let mut output = Matrix::zeros((rows, cols));
+
+    let mut offset = 0;
+    for matrix in matrices {
+        for i in 0..rows {
+            for j in 0..matrix.cols() {
+                output[(i, offset + j)] = matrix[(i, j)];
+            }
+        }
+        offset += matrix.cols();
+    }
+
+    output
+}
+
+/// The values of time step `t` of a batch of flattened sequences with `width` values per
+/// step.
+fn step(sequences: &Matrix<f32>, t: usize, width: usize) -> Matrix<f32> {
+    columns(sequences, t * width, width)
+}
+
+fn set_step(sequences: &mut Matrix<f32>, t: usize, value: &Matrix<f32>) {
+    let width = value.cols();
+    for i in 0..value.rows() {
+        for j in 0..width {
+            sequences[(i, t * width + j)] = value[(i, j)];
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        domain::random_provider,
+        math::Loss,
+        mlp::{Dense, MultiLayerPerceptron, gradient_check},
+    };
+
+    const TIME: usize = 4;
+    const FEATURES: usize = 3;
+
+    fn check(layer: impl Layer + 'static, units: usize, return_sequences: bool) {
+        random_provider::seed(12);
+
+        let outputs = if return_sequences {
+            TIME * units
+        } else {
+            units
+        };
+        let mut model = MultiLayerPerceptron::new()
+            .layer(Dense::new((2, TIME * FEATURES)))
+            .layer(layer)
+            .layer(Dense::new((outputs, 2)).activation(Activation::Tanh));
+
+        let input = Matrix::random((3, 2), -1.0..1.0);
+        let target = Matrix::random((3, 2), -1.0..1.0);
+        let errors = gradient_check(&mut model, &input, &target, &Loss::MSE, 1e-2);
+        for (layer, error) in errors.iter().enumerate() {
+            assert!(
+                *error < 1e-2,
+                "return_sequences {return_sequences}, layer {layer}: relative error {error}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_simple_rnn_gradients() {
+        for return_sequences in [false, true] {
+            check(
+                SimpleRNN::new(FEATURES, 5).return_sequences(return_sequences),
+                5,
+                return_sequences,
+            );
+        }
+    }
+
+    #[test]
+    fn test_lstm_gradients() {
+        for return_sequences in [false, true] {
+            check(
+                LSTM::new(FEATURES, 4).return_sequences(return_sequences),
+                4,
+                return_sequences,
+            );
+        }
+    }
+
+    #[test]
+    fn test_gru_gradients() {
+        for return_sequences in [false, true] {
+            check(
+                GRU::new(FEATURES, 4).return_sequences(return_sequences),
+                4,
+                return_sequences,
+            );
+        }
+    }
+
+    #[test]
+    fn test_output_shapes() {
+        random_provider::seed(3);
+
+        let input = Matrix::random((2, TIME * FEATURES), -1.0..1.0);
+        let last = LSTM::new(FEATURES, 6).forward(&input);
+        let sequence = LSTM::new(FEATURES, 6)
+            .return_sequences(true)
+            .forward(&input);
+
+        assert_eq!(last.shape(), (2, 6));
+        assert_eq!(sequence.shape(), (2, TIME * 6));
+    }
+
+    #[test]
+    fn test_forward_tensor() {
+        random_provider::seed(8);
+
+        let input = Tensor::from_vec(
+            Matrix::random((2, TIME * FEATURES), -1.0..1.0)
+                .as_ref()
+                .to_vec(),
+            (2, TIME, FEATURES),
+        );
+        let rows = Matrix::from(input.clone());
+
+        let rnn = SimpleRNN::new(FEATURES, 5);
+        let output = rnn.forward_tensor(&input);
+        assert_eq!(output.shape().dims, vec![2, 5]);
+        assert_eq!(output.data, rnn.forward(&rows).as_ref());
+
+        let lstm = LSTM::new(FEATURES, 5).return_sequences(true);
+        let output = lstm.forward_tensor(&input);
+        assert_eq!(output.shape().dims, vec![2, TIME, 5]);
+        assert_eq!(output.data, lstm.forward(&rows).as_ref());
+
+        let gru = GRU::new(FEATURES, 5).return_sequences(true);
+        assert_eq!(gru.forward_tensor(&input).data, gru.forward(&rows).as_ref());
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Recurrent layer expected a (batch, time, 3) tensor, got shape [2, 4, 2]"
+    )]
+    fn test_forward_tensor_checks_features() {
+        GRU::new(FEATURES, 2).forward_tensor(&Tensor::new((2, TIME, 2)));
+    }
+
+    #[test]
+    fn test_from_record_keeps_random_sequence() {
+        let lstm = LSTM::new(FEATURES, 4);
+        let record = lstm.record(true).unwrap();
+
+        let next = random_provider::preserve_state(random_provider::random::<u64>);
+        assert_eq!(LSTM::from_record(&record).unwrap(), lstm);
+        assert_eq!(random_provider::random::<u64>(), next);
+    }
+
+    #[test]
+    fn test_return_sequences_ends_with_last_state() {
+        random_provider::seed(4);
+
+        let gru = GRU::new(FEATURES, 3);
+        let input = Matrix::random((2, TIME * FEATURES), -1.0..1.0);
+        let last = gru.forward(&input);
+        let sequence = gru.clone().return_sequences(true).forward(&input);
+
+        assert_eq!(step(&sequence, TIME - 1, 3), last);
+    }
+
+    #[test]
+    fn test_simple_rnn_step() {
+        let mut rnn = SimpleRNN::new(1, 1);
+        rnn.weights.input = Matrix::from(vec![0.5]);
+        rnn.weights.hidden = Matrix::from(vec![2.0]);
+        rnn.weights.biases = Matrix::from(vec![0.1]);
+
+        let output = rnn.forward(&Matrix::from(vec![1.0, -1.0]));
+        let h1 = (0.5f32 + 0.1).tanh();
+        let h2 = (-0.5 + 2.0 * h1 + 0.1).tanh();
+        assert!((output[(0, 0)] - h2).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_lstm_learns_to_sum_a_sequence() {
+        random_provider::seed(6);
+
+        let sequences = (0..32)
+            .map(|_| Matrix::random((1, 5), 0.0..0.2))
+            .collect::<Vec<Matrix<f32>>>();
+        let targets = sequences
+            .iter()
+            .map(|s| Matrix::from(vec![s.iter().sum::<f32>()]))
+            .collect::<Vec<Matrix<f32>>>();
+
+        let mut model = MultiLayerPerceptron::new()
+            .layer(LSTM::new(1, 8))
+            .layer(Dense::new((8, 1)));
+        let history = model.fit_epochs(
+            &sequences,
+            &targets,
+            &Optimizer::adam(0.01),
+            &Loss::MSE,
+            200,
+        );
+
+        assert!(
+            history.last_loss().unwrap() < history.loss[0] / 10.0,
+            "loss: {:?}",
+            history.last_loss()
+        );
+    }
+}
@@ -0,0 +1,479 @@
+use super::MultiLayerPerceptron;
+use crate::{
+    Matrix,
+    math::{Loss, Optimizer},
+};
+
+/// Returned by a [`Callback`] to tell the [`Trainer`] whether to keep training.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Control {
+    Continue,
+    Stop,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BatchLog {
+    pub epoch: usize,
+    pub batch: usize,
+    pub loss: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EpochLog {
+    pub epoch: usize,
+    pub loss: f32,
+    pub val_loss: Option<f32>,
+}
+
+/// Hooks invoked by the [`Trainer`] between optimizer steps. Both methods default to
+/// [`Control::Continue`], so implementors only override the events they care about.
+pub trait Callback {
+    fn on_batch_end(&mut self, _model: &MultiLayerPerceptron, _log: &BatchLog) -> Control {
+        Control::Continue
+    }
+
+    fn on_epoch_end(&mut self, _model: &MultiLayerPerceptron, _log: &EpochLog) -> Control {
+        Control::Continue
+    }
+}
+
+impl<F> Callback for F
+where
+    F: FnMut(&MultiLayerPerceptron, &EpochLog) -> Control,
+{
+    fn on_epoch_end(&mut self, model: &MultiLayerPerceptron, log: &EpochLog) -> Control {
+        self(model, log)
+    }
+}
+
+/// The per-epoch record of a training run. `val_loss` is only populated when the
+/// [`Trainer`] was given validation data or a validation split.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct History {
+    pub loss: Vec<f32>,
+    pub val_loss: Vec<f32>,
+    pub best_epoch: Option<usize>,
+    pub stopped_epoch: Option<usize>,
+}
+
+impl History {
+    pub fn epochs(&self) -> usize {
+        self.loss.len()
+    }
+
+    pub fn last_loss(&self) -> Option<f32> {
+        self.loss.last().copied()
+    }
+
+    pub fn last_val_loss(&self) -> Option<f32> {
+        self.val_loss.last().copied()
+    }
+}
+
+enum Validation<'a> {
+    None,
+    Split(f32),
+    Data(&'a [Matrix<f32>], &'a [Matrix<f32>]),
+}
+
+/// Tracks the monitored loss for early stopping and keeps a copy of the best weights.
+struct EarlyStopping {
+    patience: usize,
+    min_delta: f32,
+    best_loss: f32,
+    best_epoch: Option<usize>,
+    best_weights: Option<Vec<Matrix<f32>>>,
+    wait: usize,
+}
+
+impl EarlyStopping {
+    fn new(patience: usize, min_delta: f32) -> Self {
+        EarlyStopping {
+            patience,
+            min_delta,
+            best_loss: f32::INFINITY,
+            best_epoch: None,
+            best_weights: None,
+            wait: 0,
+        }
+    }
+
+    fn step(&mut self, model: &MultiLayerPerceptron, epoch: usize, loss: f32) -> Control {
+        if loss < self.best_loss - self.min_delta {
+            self.best_loss = loss;
+            self.best_epoch = Some(epoch);
+            self.best_weights = Some(model.weights());
+            self.wait = 0;
+            return Control::Continue;
+        }
+
+        self.wait += 1;
+        if self.wait >= self.patience {
+            Control::Stop
+        } else {
+            Control::Continue
+        }
+    }
+}
+
+pub struct Trainer<'a> {
+    optimizer: Optimizer,
+    loss: Loss,
+    epochs: usize,
+    callbacks: Vec<Box<dyn Callback + 'a>>,
+    validation: Validation<'a>,
+    early_stopping: Option<EarlyStopping>,
+}
+
+impl<'a> Trainer<'a> {
+    pub fn new(optimizer: Optimizer, loss: Loss) -> Self {
+        Trainer {
+            optimizer,
+            loss,
+            epochs: 1,
+            callbacks: Vec::new(),
+            validation: Validation::None,
+            early_stopping: None,
+        }
+    }
+
+    pub fn epochs(mut self, epochs: usize) -> Self {
+        self.epochs = epochs;
+        self
+    }
+
+    pub fn callback<C: Callback + 'a>(mut self, callback: C) -> Self {
+        self.callbacks.push(Box::new(callback));
+        self
+    }
+
+    /// Holds out the last `fraction` of the training samples as a validation set. The
+    /// held out samples are never trained on.
+    pub fn validation_split(mut self, fraction: f32) -> Self {
+        if !(0.0..1.0).contains(&fraction) {
+            panic!("Validation split must be in the range [0, 1)");
+        }
+
+        self.validation = Validation::Split(fraction);
+        self
+    }
+
+    pub fn validation_data(mut self, input: &'a [Matrix<f32>], target: &'a [Matrix<f32>]) -> Self {
+        if input.len() != target.len() {
+            panic!("Input and target must contain the same number of samples");
+        }
+
+        self.validation = Validation::Data(input, target);
+        self
+    }
+
+    /// Stops training once the monitored loss has not improved for `patience` epochs and
+    /// restores the weights from the best epoch. The validation loss is monitored when
+    /// validation data is available, otherwise the training loss is.
+    pub fn early_stopping(self, patience: usize) -> Self {
+        self.early_stopping_with_delta(patience, 0.0)
+    }
+
+    /// Like [`Trainer::early_stopping`], but only counts a change as an improvement when
+    /// the loss decreases by more than `min_delta`.
+    pub fn early_stopping_with_delta(mut self, patience: usize, min_delta: f32) -> Self {
+        if patience == 0 {
+            panic!("Patience must be greater than zero");
+        }
+
+        self.early_stopping = Some(EarlyStopping::new(patience, min_delta));
+        self
+    }
+
+    /// Trains `model` for the configured number of epochs, shuffling the samples into
+    /// mini-batches every epoch. The loss recorded for an epoch is the mean of the losses
+    /// of the batches that ran, weighted by batch size. Training ends early as soon as any callback
+    /// returns [`Control::Stop`] or early stopping triggers; the epoch in progress is
+    /// still recorded.
+    pub fn fit(
+        &mut self,
+        model: &mut MultiLayerPerceptron,
+        input: &[Matrix<f32>],
+        target: &[Matrix<f32>],
+    ) -> History {
+        if input.len() != target.len() {
+            panic!("Input and target must contain the same number of samples");
+        }
+
+        let (input, target, validation) = match self.validation {
+            Validation::None => (input, target, None),
+            Validation::Split(fraction) => {
+                let split = input.len() - (input.len() as f32 * fraction).round() as usize;
+                (
+                    &input[..split],
+                    &target[..split],
+                    Some((&input[split..], &target[split..])),
+                )
+            }
+            Validation::Data(val_input, val_target) => {
+                (input, target, Some((val_input, val_target)))
+            }
+        };
+
+        if let Some(early_stopping) = self.early_stopping.as_mut() {
+            *early_stopping = EarlyStopping::new(early_stopping.patience, early_stopping.min_delta);
+        }
+
+        let mut history = History::default();
+
+        for epoch in 0..self.epochs {
+            let mut control = Control::Continue;
+            let mut total_loss = 0.0;
+            let mut seen = 0;
+
+            for (batch, indices) in model.batches(input.len()).into_iter().enumerate() {
+                let batch_input = Matrix::vstack(indices.iter().map(|&sample| &input[sample]));
+                let batch_target = Matrix::vstack(indices.iter().map(|&sample| &target[sample]));
+
+                let loss =
+                    model.fit_batch(&batch_input, &batch_target, &self.optimizer, &self.loss);
+                total_loss += loss * indices.len() as f32;
+                seen += indices.len();
+
+                let log = BatchLog { epoch, batch, loss };
+                for callback in self.callbacks.iter_mut() {
+                    if callback.on_batch_end(model, &log) == Control::Stop {
+                        control = Control::Stop;
+                    }
+                }
+
+                if control == Control::Stop {
+                    break;
+                }
+            }
+
+            let loss = total_loss / seen.max(1) as f32;
+            history.loss.push(loss);
+
+            let val_loss = validation
+                .map(|(val_input, val_target)| model.evaluate(val_input, val_target, &self.loss));
+            if let Some(val_loss) = val_loss {
+                history.val_loss.push(val_loss);
+            }
+
+            let log = EpochLog {
+                epoch,
+                loss,
+                val_loss,
+            };
+            for callback in self.callbacks.iter_mut() {
+                if callback.on_epoch_end(model, &log) == Control::Stop {
+                    control = Control::Stop;
+                }
+            }
+
+            if let Some(early_stopping) = self.early_stopping.as_mut() {
+                let monitored = val_loss.unwrap_or(loss);
+                if early_stopping.step(model, epoch, monitored) == Control::Stop {
+                    control = Control::Stop;
+                }
+            }
+
+            if control == Control::Stop {
+                history.stopped_epoch = Some(epoch);
+                break;
+            }
+        }
+
+        if let Some(early_stopping) = self.early_stopping.as_mut() {
+            history.best_epoch = early_stopping.best_epoch;
+            if let Some(weights) = early_stopping.best_weights.take() {
+                model.set_weights(weights);
+            }
+        }
+
+        history
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        domain::random_provider,
+        math::Activation,
+        mlp::{
+            BatchNorm1d, Dense,
+            perceptron::test::{xor, xor_mlp},
+        },
+    };
+
+    #[test]
+    fn test_trainer_records_history() {
+        let (features, targets) = xor();
+        let mut mlp = xor_mlp();
+
+        let history = Trainer::new(Optimizer::adam(0.01), Loss::MSE)
+            .epochs(300)
+            .fit(&mut mlp, &features, &targets);
+
+        assert_eq!(history.epochs(), 300);
+        assert!(history.last_loss().unwrap() < history.loss[0]);
+        assert!(history.last_loss().unwrap() < 0.05);
+    }
+
+    #[test]
+    fn test_trainer_callback_stops_training() {
+        let (features, targets) = xor();
+        let mut mlp = xor_mlp();
+
+        let mut seen = Vec::new();
+        let history = Trainer::new(Optimizer::adam(0.01), Loss::MSE)
+            .epochs(100)
+            .callback(|_: &MultiLayerPerceptron, log: &EpochLog| {
+                seen.push(log.epoch);
+                if log.epoch == 2 {
+                    Control::Stop
+                } else {
+                    Control::Continue
+                }
+            })
+            .fit(&mut mlp, &features, &targets);
+
+        assert_eq!(history.epochs(), 3);
+        assert_eq!(seen, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_trainer_batch_callback() {
+        struct BatchCounter<'a>(&'a mut usize);
+
+        impl Callback for BatchCounter<'_> {
+            fn on_batch_end(&mut self, _: &MultiLayerPerceptron, _: &BatchLog) -> Control {
+                *self.0 += 1;
+                Control::Continue
+            }
+        }
+
+        let (features, targets) = xor();
+        let mut mlp = xor_mlp().batch_size(3);
+        let mut batches = 0;
+
+        Trainer::new(Optimizer::SGD(0.1), Loss::MSE)
+            .epochs(5)
+            .callback(BatchCounter(&mut batches))
+            .fit(&mut mlp, &features, &targets);
+
+        assert_eq!(batches, 10);
+    }
+
+    #[test]
+    fn test_trainer_partial_epoch_loss() {
+        struct StopAfterBatch<'a>(&'a mut Vec<f32>);
+
+        impl Callback for StopAfterBatch<'_> {
+            fn on_batch_end(&mut self, _: &MultiLayerPerceptron, log: &BatchLog) -> Control {
+                self.0.push(log.loss);
+                Control::Stop
+            }
+        }
+
+        let (features, targets) = xor();
+        let mut mlp = xor_mlp().batch_size(3);
+        let mut losses = Vec::new();
+
+        let history = Trainer::new(Optimizer::SGD(0.1), Loss::MSE)
+            .epochs(5)
+            .callback(StopAfterBatch(&mut losses))
+            .fit(&mut mlp, &features, &targets);
+
+        assert_eq!(history.loss, losses);
+    }
+
+    #[test]
+    fn test_trainer_validation_split() {
+        let (features, targets) = xor();
+        let mut mlp = xor_mlp();
+
+        let mut has_val_loss = Vec::new();
+        let history = Trainer::new(Optimizer::adam(0.01), Loss::MSE)
+            .epochs(4)
+            .validation_split(0.25)
+            .callback(|_: &MultiLayerPerceptron, log: &EpochLog| {
+                has_val_loss.push(log.val_loss.is_some());
+                Control::Continue
+            })
+            .fit(&mut mlp, &features, &targets);
+
+        assert_eq!(history.loss.len(), 4);
+        assert_eq!(history.val_loss.len(), 4);
+        assert_eq!(has_val_loss, vec![true; 4]);
+        assert_eq!(
+            history.last_val_loss().unwrap(),
+            mlp.evaluate(&features[3..], &targets[3..], &Loss::MSE)
+        );
+    }
+
+    #[test]
+    fn test_trainer_early_stopping_patience() {
+        let (features, targets) = xor();
+        let mut mlp = xor_mlp();
+
+        // A zero learning rate never improves the loss, so training stops once the
+        // patience runs out after the first epoch.
+        let history = Trainer::new(Optimizer::SGD(0.0), Loss::MSE)
+            .epochs(100)
+            .validation_data(&features, &targets)
+            .early_stopping(3)
+            .fit(&mut mlp, &features, &targets);
+
+        assert_eq!(history.epochs(), 4);
+        assert_eq!(history.best_epoch, Some(0));
+        assert_eq!(history.stopped_epoch, Some(3));
+    }
+
+    #[test]
+    fn test_trainer_early_stopping_restores_best_weights() {
+        let (features, targets) = xor();
+        assert_restores_best_epoch(xor_mlp(), &features, &targets);
+    }
+
+    #[test]
+    fn test_trainer_early_stopping_restores_batch_norm_statistics() {
+        let (features, targets) = xor();
+        let mlp = random_provider::scoped_seed(6, || {
+            MultiLayerPerceptron::new()
+                .layer(Dense::new((2, 8)).activation(Activation::Tanh))
+                .layer(BatchNorm1d::new(8))
+                .layer(Dense::new((8, 1)).activation(Activation::Sigmoid))
+        });
+
+        assert_restores_best_epoch(mlp.batch_size(2), &features, &targets);
+    }
+
+    fn assert_restores_best_epoch(
+        mut mlp: MultiLayerPerceptron,
+        features: &[Matrix<f32>],
+        targets: &[Matrix<f32>],
+    ) {
+        // Validating against the inverted targets makes the validation loss climb as the
+        // model learns, so the best epoch is well before training stops.
+        let inverted = targets
+            .iter()
+            .map(|target| Matrix::from(vec![1.0 - target[(0, 0)]]))
+            .collect::<Vec<Matrix<f32>>>();
+
+        let history = Trainer::new(Optimizer::adam(0.01), Loss::MSE)
+            .epochs(500)
+            .validation_data(features, &inverted)
+            .early_stopping(5)
+            .fit(&mut mlp, features, targets);
+
+        let best_epoch = history.best_epoch.unwrap();
+        let best = history.val_loss[best_epoch];
+
+        assert!(best_epoch < history.stopped_epoch.unwrap());
+        assert!(history.last_val_loss().unwrap() > best);
+        assert!(history.val_loss.iter().all(|&loss| loss >= best));
+        assert_eq!(mlp.evaluate(features, &inverted, &Loss::MSE), best);
+    }
+}
@@ -0,0 +1,321 @@
+use super::{Dense, Layer, LayerNorm, LayerRecord, MultiHeadAttention};
+use crate::{
+    Matrix,
+    math::{Activation, Initializer, Optimizer, Tensor},
+    mlp::attention::sequence_rows,
+};
+use std::io;
+
+/// A Transformer encoder block over `(batch, seq, dim)` sequences, taken as tensors by
+/// [`TransformerEncoder::forward_tensor`] or laid out as for [`MultiHeadAttention`].
+/// Self-attention and a position-wise feed-forward network of two [`Dense`] layers each
+/// add their output back onto their input, followed by a [`LayerNorm`]:
+///
+/// ```text
+/// x = norm(x + attention(x))
+/// x = norm(x + dense(relu(dense(x))))
+/// ```
+#[derive(PartialEq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TransformerEncoder {
+    dim: usize,
+    attention: MultiHeadAttention,
+    attention_norm: LayerNorm,
+    hidden: Dense,
+    output: Dense,
+    output_norm: LayerNorm,
+    /// The input, attention output, normalized attention output, hidden activations and
+    /// feed-forward output of the last training pass. All but the first two hold one
+    /// position per row.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    tape: Vec<Matrix<f32>>,
+}
+
+impl TransformerEncoder {
+    /// Creates a block with `heads` attention heads and `hidden` units in the feed-forward
+    /// network.
+    pub fn new(dim: usize, heads: usize, hidden: usize) -> Self {
+        TransformerEncoder {
+            dim,
+            attention: MultiHeadAttention::new(dim, heads),
+            attention_norm: LayerNorm::new(dim),
+            hidden: Dense::new((dim, hidden))
+                .activation(Activation::ReLU)
+                .weight_initializer(Initializer::HeUniform)
+                .bias_initializer(Initializer::Zeros),
+            output: Dense::new((hidden, dim))
+                .weight_initializer(Initializer::XavierUniform)
+                .bias_initializer(Initializer::Zeros),
+            output_norm: LayerNorm::new(dim),
+            tape: Vec::new(),
+        }
+    }
+
+    /// Rebuilds a layer from a record written by [`Layer::record`].
+    pub fn from_record(record: &LayerRecord) -> io::Result<Self> {
+        Ok(TransformerEncoder {
+            dim: record.get_attribute("dim")?,
+            attention: MultiHeadAttention::from_record(
+                &record.get_nested("attention", "multi_head_attention"),
+            )?,
+            attention_norm: LayerNorm::from_record(
+                &record.get_nested("attention_norm", "layer_norm"),
+            )?,
+            hidden: Dense::from_record(&record.get_nested("hidden", "dense"))?,
+            output: Dense::from_record(&record.get_nested("output", "dense"))?,
+            output_norm: LayerNorm::from_record(&record.get_nested("output_norm", "layer_norm"))?,
+            tape: Vec::new(),
+        })
+    }
+
+    /// Restricts which positions attend to which, see [`MultiHeadAttention::mask`].
+    pub fn mask(mut self, mask: Matrix<bool>) -> Self {
+        self.attention = self.attention.mask(mask);
+        self
+    }
+
+    /// Like [`Layer::forward`], but on a `(batch, seq, dim)` tensor, with an optional
+    /// `(batch, seq)` key padding mask as for [`MultiHeadAttention::forward_tensor`].
+    pub fn forward_tensor(
+        &self,
+        input: &Tensor<f32>,
+        padding: Option<&Matrix<bool>>,
+    ) -> Tensor<f32> {
+        let shape = input.shape().dims.clone();
+        let seq = shape.get(1).copied().unwrap_or_default();
+        let output = self.forward_padded(&sequence_rows(input, seq, self.dim), padding);
+        Tensor::from_vec(output.as_ref().to_vec(), shape)
+    }
+
+    /// Like [`Layer::forward`], with an optional key padding mask as for
+    /// [`MultiHeadAttention::forward_tensor`].
+    pub fn forward_padded(
+        &self,
+        input: &Matrix<f32>,
+        padding: Option<&Matrix<bool>>,
+    ) -> Matrix<f32> {
+        let attended = self.positions(self.attention.forward_padded(input, padding) + input);
+        let normalized = self.attention_norm.forward(&attended);
+        let projected = self.output.forward(&self.hidden.forward(&normalized));
+
+        self.output_norm
+            .forward(&(normalized + projected))
+            .reshape(input.shape())
+    }
+
+    /// Like [`Layer::feed_forward`], with an optional key padding mask as for
+    /// [`MultiHeadAttention::forward_tensor`].
+    pub fn feed_forward_padded(
+        &mut self,
+        input: &Matrix<f32>,
+        padding: Option<&Matrix<bool>>,
+    ) -> Matrix<f32> {
+        let attention = self.attention.feed_forward_padded(input, padding);
+        let normalized = self
+            .attention_norm
+            .feed_forward(&self.positions(input + &attention));
+        let hidden = self.hidden.feed_forward(&normalized);
+        let projected = self.output.feed_forward(&hidden);
+        let output = self.output_norm.feed_forward(&(&normalized + &projected));
+
+        self.tape = vec![input.clone(), attention, normalized, hidden, projected];
+        output.reshape(input.shape())
+    }
+
+    /// Splits a batch of sequences into one position per row.
+    fn positions(&self, input: Matrix<f32>) -> Matrix<f32> {
+        let rows = input.len() / self.dim.max(1);
+        input.reshape((rows, self.dim))
+    }
+}
+
+impl Layer for TransformerEncoder {
+    fn forward(&self, input: &Matrix<f32>) -> Matrix<f32> {
+        self.forward_padded(input, None)
+    }
+
+    fn feed_forward(&mut self, input: &Matrix<f32>) -> Matrix<f32> {
+        self.feed_forward_padded(input, None)
+    }
+
+    fn backpropagate(
+        &mut self,
+        error: &Matrix<f32>,
+        prev_input: &Matrix<f32>,
+        _: &Matrix<f32>,
+    ) -> Matrix<f32> {
+        let [input, attention, normalized, hidden, projected] = &self.tape[..] else {
+            panic!("Transformer encoder must run a training pass before backpropagating");
+        };
+
+        // Layer normalization keeps its own cache, so its input and output are not needed.
+        let empty = Matrix::new(0, 0);
+        let residual_error =
+            self.output_norm
+                .backpropagate(&self.positions(error.clone()), &empty, &empty);
+        let hidden_error = self
+            .output
+            .backpropagate(&residual_error, hidden, projected);
+        let normalized_error =
+            residual_error + self.hidden.backpropagate(&hidden_error, normalized, hidden);
+
+        let attended_error = self
+            .attention_norm
+            .backpropagate(&normalized_error, &empty, &empty)
+            .reshape(prev_input.shape());
+        let attention_error = self
+            .attention
+            .backpropagate(&attended_error, input, attention);
+
+        attended_error + attention_error
+    }
+
+    fn update(&mut self, optimizer: &Optimizer) {
+        self.attention.update(optimizer);
+        self.attention_norm.update(optimizer);
+        self.hidden.update(optimizer);
+        self.output.update(optimizer);
+        self.output_norm.update(optimizer);
+    }
+
+    fn parameters(&self) -> Vec<&Matrix<f32>> {
+        let mut parameters = self.attention.parameters();
+        parameters.extend(self.attention_norm.parameters());
+        parameters.extend(self.hidden.parameters());
+        parameters.extend(self.output.parameters());
+        parameters.extend(self.output_norm.parameters());
+        parameters
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Matrix<f32>> {
+        let mut parameters = self.attention.parameters_mut();
+        parameters.extend(self.attention_norm.parameters_mut());
+        parameters.extend(self.hidden.parameters_mut());
+        parameters.extend(self.output.parameters_mut());
+        parameters.extend(self.output_norm.parameters_mut());
+        parameters
+    }
+
+    fn gradients(&self) -> Vec<&Matrix<f32>> {
+        let mut gradients = self.attention.gradients();
+        gradients.extend(self.attention_norm.gradients());
+        gradients.extend(self.hidden.gradients());
+        gradients.extend(self.output.gradients());
+        gradients.extend(self.output_norm.gradients());
+        gradients
+    }
+
+    fn gradients_mut(&mut self) -> Vec<&mut Matrix<f32>> {
+        let mut gradients = self.attention.gradients_mut();
+        gradients.extend(self.attention_norm.gradients_mut());
+        gradients.extend(self.hidden.gradients_mut());
+        gradients.extend(self.output.gradients_mut());
+        gradients.extend(self.output_norm.gradients_mut());
+        gradients
+    }
+
+    fn record(&self, optimizer_state: bool) -> Option<LayerRecord> {
+        Some(
+            LayerRecord::new("transformer_encoder")
+                .attribute("dim", self.dim)
+                .nested("attention", self.attention.record(optimizer_state)?)
+                .nested(
+                    "attention_norm",
+                    self.attention_norm.record(optimizer_state)?,
+                )
+                .nested("hidden", self.hidden.record(optimizer_state)?)
+                .nested("output", self.output.record(optimizer_state)?)
+                .nested("output_norm", self.output_norm.record(optimizer_state)?),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        domain::random_provider,
+        math::{Loss, Tensor},
+        mlp::{MultiLayerPerceptron, causal_mask, gradient_check},
+    };
+
+    #[test]
+    fn test_transformer_encoder_gradients() {
+        random_provider::seed(9);
+
+        let mut model = MultiLayerPerceptron::new()
+            .layer(Dense::new((2, 12)).weight_initializer(Initializer::XavierUniform))
+            .layer(TransformerEncoder::new(4, 2, 8).mask(causal_mask(3)))
+            .layer(Dense::new((12, 2)).activation(Activation::Tanh));
+
+        let input = Matrix::random((3, 2), -1.0..1.0);
+        let target = Matrix::random((3, 2), -1.0..1.0);
+        let errors = gradient_check(&mut model, &input, &target, &Loss::MSE, 1e-2);
+        for (layer, error) in errors.iter().enumerate() {
+            assert!(*error < 2e-2, "layer {layer}: relative error {error}");
+        }
+    }
+
+    #[test]
+    fn test_transformer_encoder_normalizes_positions() {
+        random_provider::seed(10);
+
+        let mut encoder = TransformerEncoder::new(6, 3, 12);
+        let input = Tensor::from_vec(
+            Matrix::random((2, 24), -2.0..2.0).as_ref().to_vec(),
+            (2, 4, 6),
+        );
+        let output = encoder.forward_tensor(&input, None);
+
+        assert_eq!(output.shape().dims, vec![2, 4, 6]);
+        for position in output.data.chunks(6) {
+            let mean = position.iter().sum::<f32>() / 6.0;
+            let variance = position.iter().map(|x| (x - mean).powi(2)).sum::<f32>() / 6.0;
+            assert!(mean.abs() < 1e-5, "mean {mean}");
+            assert!((variance - 1.0).abs() < 1e-3, "variance {variance}");
+        }
+
+        let rows = Matrix::from(input);
+        assert_eq!(encoder.forward(&rows).as_ref(), &output.data[..]);
+        assert_eq!(encoder.feed_forward(&rows).as_ref(), &output.data[..]);
+    }
+
+    #[test]
+    fn test_transformer_encoder_padding_mask() {
+        random_provider::seed(12);
+
+        let mut encoder = TransformerEncoder::new(4, 2, 8);
+        let input = Tensor::from_vec(
+            Matrix::random((2, 12), -1.0..1.0).as_ref().to_vec(),
+            (2, 3, 4),
+        );
+        let padding = Matrix::from(vec![true, false, false, true, true, false]).reshape((2, 3));
+        let mut changed = input.clone();
+        changed.data[4..12].iter_mut().for_each(|x| *x = 0.0);
+        changed.data[20..24].iter_mut().for_each(|x| *x = 0.0);
+
+        let output = encoder.forward_tensor(&input, Some(&padding));
+        let repadded = encoder.forward_tensor(&changed, Some(&padding));
+        assert_eq!(output.data[..4], repadded.data[..4]);
+        assert_eq!(output.data[12..20], repadded.data[12..20]);
+
+        let rows = Matrix::from(input);
+        assert_eq!(
+            encoder.forward_padded(&rows, Some(&padding)).as_ref(),
+            &output.data[..]
+        );
+        assert_eq!(
+            encoder.feed_forward_padded(&rows, Some(&padding)).as_ref(),
+            &output.data[..]
+        );
+    }
+
+    #[test]
+    fn test_record_round_trip() {
+        random_provider::seed(11);
+
+        let encoder = TransformerEncoder::new(4, 2, 8).mask(causal_mask(5));
+        let loaded = TransformerEncoder::from_record(&encoder.record(true).unwrap()).unwrap();
+        assert_eq!(loaded, encoder);
+    }
+}